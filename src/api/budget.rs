@@ -0,0 +1,99 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use thiserror::Error;
+
+use crate::api::Error;
+
+/// The request budget is exhausted
+#[derive(Error, Debug)]
+#[error("too many requests")]
+pub struct BudgetExceededError;
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// A token bucket limiting the total number of
+/// requests per second across all clients.
+pub struct TokenBucket {
+    rate: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl TokenBucket {
+    /// Create a new bucket allowing `rps` requests per second.
+    /// Up to `rps` requests may arrive in a burst.
+    pub fn new(rps: u32) -> Self {
+        let rate = rps.max(1) as f64;
+        Self {
+            rate,
+            bucket: Mutex::new(Bucket {
+                tokens: rate,
+                updated_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take a token from the bucket. If the bucket is empty,
+    /// the time until the next token is available is returned.
+    pub fn acquire(&self) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.rate);
+        bucket.updated_at = now;
+
+        if bucket.tokens < 1.0 {
+            let wait = (1.0 - bucket.tokens) / self.rate;
+            return Err(Duration::from_secs_f64(wait));
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+/// Reject requests exceeding the global budget
+/// with 503 and a Retry-After header.
+pub async fn limit(
+    State(bucket): State<Arc<TokenBucket>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match bucket.acquire() {
+        Ok(()) => next.run(req).await,
+        Err(wait) => {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            let mut res = Error::from(BudgetExceededError).into_response();
+            res.headers_mut()
+                .insert(header::RETRY_AFTER, retry_after.into());
+            res
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let bucket = TokenBucket::new(2);
+        assert!(bucket.acquire().is_ok());
+        assert!(bucket.acquire().is_ok());
+        let wait = bucket.acquire().unwrap_err();
+        assert!(wait <= Duration::from_millis(500));
+
+        std::thread::sleep(wait);
+        assert!(bucket.acquire().is_ok());
+    }
+}
@@ -4,6 +4,12 @@ use axum::{
 };
 use serde::Serialize;
 
+use crate::{
+    api::budget::BudgetExceededError,
+    bird::{NotFoundError, UnavailableError},
+    parsers::routes_worker::WorkerError,
+};
+
 /// Error Response
 #[derive(Serialize, Clone, Debug)]
 struct ErrorResponse {
@@ -27,7 +33,16 @@ where
 /// Implement IntoResponse for Error
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        let code = StatusCode::INTERNAL_SERVER_ERROR;
+        let code = if self.0.is::<NotFoundError>() {
+            StatusCode::NOT_FOUND
+        } else if self.0.is::<UnavailableError>()
+            || self.0.is::<WorkerError>()
+            || self.0.is::<BudgetExceededError>()
+        {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
         let err = ErrorResponse {
             code: code.as_u16(),
             error: format!("{}", self.0),
@@ -36,3 +51,27 @@ impl IntoResponse for Error {
         (code, body).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_status_code() {
+        let err: Error = anyhow::anyhow!("something failed").into();
+        let res = err.into_response();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let err: Error = UnavailableError.into();
+        let res = err.into_response();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let err: Error = WorkerError.into();
+        let res = err.into_response();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let err: Error = NotFoundError("protocol R1".into()).into();
+        let res = err.into_response();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
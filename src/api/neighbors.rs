@@ -1,18 +1,116 @@
+use std::collections::BTreeMap;
+
 use anyhow::Result;
-use axum::extract::Path;
+use axum::extract::{Path, Query, State};
+use serde::Deserialize;
 
 use crate::{
     api::{
-        responses::{NeighborsResponse, RoutesResponse},
+        responses::{
+            encode_routes, NeighborsListResponse, NeighborsResponse,
+            ProtocolsByAsnResponse, ProtocolsFormat, ProtocolsListResponse,
+            RoutesOptions, RoutesSummaryResponse,
+        },
         Error,
     },
     bird::{Birdc, ProtocolID},
+    state::{Neighbor, NeighborsMap, RoutesSummary},
 };
 
+/// Query parameters for listing protocols
+#[derive(Deserialize, Debug, Default)]
+pub struct ProtocolsQuery {
+    #[serde(default)]
+    format: ProtocolsFormat,
+}
+
+/// Query the protocols from bird and encode them
+/// as a map or an array.
+async fn fetch_protocols(
+    birdc: &Birdc,
+    format: ProtocolsFormat,
+) -> Result<String> {
+    let body = match format {
+        ProtocolsFormat::Map => {
+            let protocols = birdc.show_protocols_all().await?;
+            let response = NeighborsResponse {
+                protocols,
+                ..Default::default()
+            };
+            serde_json::to_string(&response)?
+        }
+        ProtocolsFormat::Array => {
+            let protocols = birdc.show_neighbors().await?;
+            let response = ProtocolsListResponse {
+                protocols,
+                ..Default::default()
+            };
+            serde_json::to_string(&response)?
+        }
+    };
+    Ok(body)
+}
+
 /// List all neighbors (show protocols all, filter BGP)
-pub async fn list() -> Result<String, Error> {
-    let birdc = Birdc::default();
+pub async fn list(
+    State(birdc): State<Birdc>,
+    Query(query): Query<ProtocolsQuery>,
+) -> Result<String, Error> {
+    let body = fetch_protocols(&birdc, query.format).await?;
+    Ok(body)
+}
+
+/// List all neighbors as a list
+pub async fn list_neighbors(
+    State(birdc): State<Birdc>,
+) -> Result<String, Error> {
+    let neighbors = birdc.show_neighbors().await?;
+
+    let response = NeighborsListResponse {
+        neighbors,
+        ..Default::default()
+    };
+    let body = serde_json::to_string(&response)?;
+    Ok(body)
+}
+
+/// Group the neighbors by their ASN. Neighbors with
+/// an unknown ASN are grouped under 0.
+fn group_by_asn(neighbors: NeighborsMap) -> BTreeMap<u32, Vec<Neighbor>> {
+    let mut groups: BTreeMap<u32, Vec<Neighbor>> = BTreeMap::new();
+    for neighbor in neighbors.into_values() {
+        groups.entry(neighbor.asn).or_default().push(neighbor);
+    }
+    for group in groups.values_mut() {
+        group.sort_by(|a, b| a.id.cmp(&b.id));
+    }
+    groups
+}
+
+/// List all neighbors grouped by ASN
+pub async fn list_by_asn(State(birdc): State<Birdc>) -> Result<String, Error> {
+    let protocols = birdc.show_protocols_all().await?;
+
+    let response = ProtocolsByAsnResponse {
+        protocols: group_by_asn(protocols),
+        ..Default::default()
+    };
+    let body = serde_json::to_string(&response)?;
+    Ok(body)
+}
+
+/// Keep only the neighbors without an established session
+fn filter_down(neighbors: NeighborsMap) -> NeighborsMap {
+    neighbors
+        .into_iter()
+        .filter(|(_, n)| n.state != "up")
+        .collect()
+}
+
+/// List all neighbors which are not up
+pub async fn list_down(State(birdc): State<Birdc>) -> Result<String, Error> {
     let protocols = birdc.show_protocols_all().await?;
+    let protocols = filter_down(protocols);
 
     let response = NeighborsResponse {
         protocols,
@@ -22,50 +120,174 @@ pub async fn list() -> Result<String, Error> {
     Ok(body)
 }
 
-/// List all routes received for a neighbor
-pub async fn list_routes_received(
+/// Get the number of received, filtered and accepted routes
+/// of a neighbor without transferring the routes.
+pub async fn routes_summary(
+    State(birdc): State<Birdc>,
     Path(id): Path<String>,
 ) -> Result<String, Error> {
-    let birdc = Birdc::default();
     let protocol = ProtocolID::parse(&id)?;
-    let routes = birdc.show_route_all_protocol(&protocol).await?;
+    let neighbor = birdc.show_protocol(&protocol).await?;
 
-    let response = RoutesResponse {
-        routes,
+    let response = RoutesSummaryResponse {
+        summary: RoutesSummary::from(&neighbor),
         ..Default::default()
     };
     let body = serde_json::to_string(&response)?;
     Ok(body)
 }
 
+/// List all routes received for a neighbor
+pub async fn list_routes_received(
+    State(birdc): State<Birdc>,
+    Path(id): Path<String>,
+    Query(query): Query<RoutesOptions>,
+) -> Result<String, Error> {
+    let protocol = ProtocolID::parse(&id)?;
+    let routes = birdc.show_route_all_protocol(&protocol).await?;
+    let body = encode_routes(&birdc, routes, &query).await?;
+    Ok(body)
+}
+
 /// List all routes filtered by a neighbor
 pub async fn list_routes_filtered(
+    State(birdc): State<Birdc>,
     Path(id): Path<String>,
+    Query(options): Query<RoutesOptions>,
 ) -> Result<String, Error> {
-    let birdc = Birdc::default();
     let protocol = ProtocolID::parse(&id)?;
     let routes = birdc.show_route_all_filtered_protocol(&protocol).await?;
-    let response = RoutesResponse {
-        routes,
-        ..Default::default()
-    };
+    let body = encode_routes(&birdc, routes, &options).await?;
+    Ok(body)
+}
 
-    let body = serde_json::to_string(&response)?;
+/// List all routes received and filtered by a neighbor
+pub async fn list_routes_all(
+    State(birdc): State<Birdc>,
+    Path(id): Path<String>,
+    Query(options): Query<RoutesOptions>,
+) -> Result<String, Error> {
+    let protocol = ProtocolID::parse(&id)?;
+    let routes = birdc
+        .show_route_all_protocol_with_filtered(&protocol)
+        .await?;
+
+    let body = encode_routes(&birdc, routes, &options).await?;
     Ok(body)
 }
 
 /// List all routes not exported
 pub async fn list_routes_noexport(
+    State(birdc): State<Birdc>,
     Path(id): Path<String>,
+    Query(options): Query<RoutesOptions>,
 ) -> Result<String, Error> {
-    let birdc = Birdc::default();
     let protocol = ProtocolID::parse(&id)?;
     let routes = birdc.show_route_all_noexport_protocol(&protocol).await?;
 
-    let response = RoutesResponse {
-        routes,
-        ..Default::default()
-    };
-    let body = serde_json::to_string(&response)?;
+    let body = encode_routes(&birdc, routes, &options).await?;
     Ok(body)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bird::mock::MockSocket;
+    use axum::{http::StatusCode, response::IntoResponse};
+    use serde_json::Value;
+
+    #[test]
+    fn test_filter_down() {
+        let states = [("R1", "up"), ("R2", "down"), ("R3", "start")];
+        let neighbors: NeighborsMap = states
+            .into_iter()
+            .map(|(id, state)| {
+                let neighbor = Neighbor {
+                    id: id.into(),
+                    state: state.into(),
+                    ..Default::default()
+                };
+                (id.to_string(), neighbor)
+            })
+            .collect();
+
+        let down = filter_down(neighbors);
+        let mut ids: Vec<&String> = down.keys().collect();
+        ids.sort();
+        assert_eq!(ids, vec!["R2", "R3"]);
+    }
+
+    #[test]
+    fn test_group_by_asn() {
+        let sessions = [("R3", 65001), ("R1", 65001), ("R2", 0), ("R4", 553)];
+        let neighbors: NeighborsMap = sessions
+            .into_iter()
+            .map(|(id, asn)| {
+                let neighbor = Neighbor {
+                    id: id.into(),
+                    asn,
+                    ..Default::default()
+                };
+                (id.to_string(), neighbor)
+            })
+            .collect();
+
+        let groups = group_by_asn(neighbors);
+        let groups: Vec<(u32, Vec<&str>)> = groups
+            .iter()
+            .map(|(asn, ns)| {
+                (*asn, ns.iter().map(|n| n.id.as_str()).collect())
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (0, vec!["R2"]),
+                (553, vec!["R4"]),
+                (65001, vec!["R1", "R3"]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_routes_summary_not_found() {
+        let reply =
+            std::fs::read_to_string("tests/birdc/show-protocols-all").unwrap();
+        let socket = MockSocket::serve(&reply);
+
+        let path = Path("R_unknown".to_string());
+        let Err(err) = routes_summary(State(socket.birdc()), path).await
+        else {
+            panic!("unknown protocol found");
+        };
+        let res = err.into_response();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_protocols_array() {
+        let reply =
+            std::fs::read_to_string("tests/birdc/show-protocols-all").unwrap();
+        let socket = MockSocket::serve(&reply);
+        let birdc = socket.birdc();
+
+        let body = fetch_protocols(&birdc, ProtocolsFormat::Array)
+            .await
+            .unwrap();
+        let json: Value = serde_json::from_str(&body).unwrap();
+        let protocols = json["protocols"].as_array().unwrap();
+        let ids: Vec<&str> = protocols
+            .iter()
+            .map(|p| p["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids[0], "R194_42");
+        assert_eq!(ids[3], "R_pp_60");
+
+        let body =
+            fetch_protocols(&birdc, ProtocolsFormat::Map).await.unwrap();
+        let json: Value = serde_json::from_str(&body).unwrap();
+        let protocols = json["protocols"].as_object().unwrap();
+        assert_eq!(protocols.len(), ids.len());
+        assert_eq!(protocols["R194_42"]["id"], "R194_42");
+    }
+}
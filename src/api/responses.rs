@@ -1,7 +1,75 @@
-use crate::state::{ApiStatus, BirdStatus, Neighbor, Route};
+use crate::{
+    bird::Birdc,
+    state::{
+        ApiStatus, BirdStatus, Community, LargeCommunity, Neighbor,
+        NeighborsMap, Route, RouteCount, RoutesSummary,
+    },
+};
+use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The output format of a response
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// The order of routes in a response
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteSort {
+    AspathLen,
+}
+
+/// Query parameters for listing routes
+#[derive(Deserialize, Debug, Default)]
+pub struct RoutesOptions {
+    pub sort: Option<RouteSort>,
+    /// Comma separated list of route fields to include
+    pub fields: Option<String>,
+    /// Attach the neighbor description to the routes
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub enrich: bool,
+}
+
+/// Deserialize a boolean query parameter. Flattened query
+/// structs only see strings, so the value is parsed here.
+fn deserialize_flag<'de, D>(
+    deserializer: D,
+) -> std::result::Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+/// Attach the description of the neighbor to each route
+pub fn enrich_routes(routes: &mut [Route], neighbors: &NeighborsMap) {
+    for route in routes.iter_mut() {
+        route.neighbor_description = route
+            .neighbor_id
+            .as_ref()
+            .and_then(|id| neighbors.get(id))
+            .map(|neighbor| neighbor.description.clone());
+    }
+}
+
+/// Sort routes. The sort is stable, so routes with
+/// equal keys keep their order.
+pub fn sort_routes(routes: &mut [Route], sort: RouteSort) {
+    match sort {
+        RouteSort::AspathLen => {
+            routes.sort_by_key(|route| route.bgp.as_path_len())
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct StatusResponse {
@@ -39,6 +107,68 @@ impl Default for NeighborsResponse {
     }
 }
 
+/// The shape of the protocols in a response
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtocolsFormat {
+    #[default]
+    Map,
+    Array,
+}
+
+/// Protocols as an array in the order of bird
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProtocolsListResponse {
+    pub api: ApiStatus,
+    pub cached_at: DateTime<Utc>,
+    pub protocols: Vec<Neighbor>,
+}
+
+impl Default for ProtocolsListResponse {
+    fn default() -> Self {
+        ProtocolsListResponse {
+            api: ApiStatus::default(),
+            cached_at: Utc::now(),
+            protocols: Vec::new(),
+        }
+    }
+}
+
+/// Protocols grouped by the ASN of the neighbor
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProtocolsByAsnResponse {
+    pub api: ApiStatus,
+    pub cached_at: DateTime<Utc>,
+    pub protocols: BTreeMap<u32, Vec<Neighbor>>,
+}
+
+impl Default for ProtocolsByAsnResponse {
+    fn default() -> Self {
+        ProtocolsByAsnResponse {
+            api: ApiStatus::default(),
+            cached_at: Utc::now(),
+            protocols: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NeighborsListResponse {
+    pub api: ApiStatus,
+    pub cached_at: DateTime<Utc>,
+    pub neighbors: Vec<Neighbor>,
+}
+
+impl Default for NeighborsListResponse {
+    fn default() -> Self {
+        NeighborsListResponse {
+            api: ApiStatus::default(),
+            cached_at: Utc::now(),
+            neighbors: Vec::new(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RoutesResponse {
     pub api: ApiStatus,
@@ -55,3 +185,362 @@ impl Default for RoutesResponse {
         }
     }
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RoutesSummaryResponse {
+    pub api: ApiStatus,
+    pub cached_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub summary: RoutesSummary,
+}
+
+impl Default for RoutesSummaryResponse {
+    fn default() -> Self {
+        RoutesSummaryResponse {
+            api: ApiStatus::default(),
+            cached_at: Utc::now(),
+            summary: RoutesSummary::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RouteCountResponse {
+    pub api: ApiStatus,
+    pub cached_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub count: RouteCount,
+}
+
+impl Default for RouteCountResponse {
+    fn default() -> Self {
+        RouteCountResponse {
+            api: ApiStatus::default(),
+            cached_at: Utc::now(),
+            count: RouteCount::default(),
+        }
+    }
+}
+
+/// A community and the number of routes it was seen on
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CommunityCount<T> {
+    pub community: T,
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommunitiesResponse {
+    pub api: ApiStatus,
+    pub cached_at: DateTime<Utc>,
+    pub communities: Vec<CommunityCount<Community>>,
+    pub large_communities: Vec<CommunityCount<LargeCommunity>>,
+}
+
+impl Default for CommunitiesResponse {
+    fn default() -> Self {
+        CommunitiesResponse {
+            api: ApiStatus::default(),
+            cached_at: Utc::now(),
+            communities: Vec::new(),
+            large_communities: Vec::new(),
+        }
+    }
+}
+
+/// Keep only the requested fields of a serialized route.
+/// Fields of the BGP info can be selected directly,
+/// e.g. `as_path`.
+fn project_route(route: &mut Map<String, Value>, fields: &HashSet<&str>) {
+    route.retain(|key, value| {
+        if fields.contains(key.as_str()) {
+            return true;
+        }
+        match value {
+            Value::Object(bgp) if key == "bgp" => {
+                bgp.retain(|key, _| fields.contains(key.as_str()));
+                !bgp.is_empty()
+            }
+            _ => false,
+        }
+    });
+}
+
+/// Serialize a routes response. If `fields` is set, only the
+/// listed fields of the routes are included.
+pub fn routes_response_to_json(
+    response: &RoutesResponse,
+    fields: Option<&str>,
+) -> Result<String> {
+    let Some(fields) = fields else {
+        return Ok(serde_json::to_string(response)?);
+    };
+    let fields: HashSet<&str> = fields.split(',').map(str::trim).collect();
+
+    let mut response = serde_json::to_value(response)?;
+    if let Some(Value::Array(routes)) = response.get_mut("routes") {
+        for route in routes.iter_mut() {
+            if let Value::Object(route) = route {
+                project_route(route, &fields);
+            }
+        }
+    }
+    Ok(serde_json::to_string(&response)?)
+}
+
+/// Sort the routes and attach the neighbor descriptions
+/// as requested by the options.
+pub async fn apply_routes_options(
+    birdc: &Birdc,
+    routes: &mut [Route],
+    options: &RoutesOptions,
+) -> Result<()> {
+    if let Some(sort) = options.sort {
+        sort_routes(routes, sort);
+    }
+    if options.enrich {
+        let protocols = birdc.show_protocols_all().await?;
+        enrich_routes(routes, &protocols);
+    }
+    Ok(())
+}
+
+/// Apply the options to the routes and serialize them
+/// as a routes response.
+pub async fn encode_routes(
+    birdc: &Birdc,
+    mut routes: Vec<Route>,
+    options: &RoutesOptions,
+) -> Result<String> {
+    apply_routes_options(birdc, &mut routes, options).await?;
+    let response = RoutesResponse {
+        routes,
+        ..Default::default()
+    };
+    routes_response_to_json(&response, options.fields.as_deref())
+}
+
+/// Format an optional CSV field, None is empty
+fn optional_field(value: Option<u32>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Quote a CSV field if required
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// The columns of the CSV encoding of routes
+const CSV_COLUMNS: [&str; 8] = [
+    "network",
+    "neighbor_id",
+    "gateway",
+    "as_path",
+    "origin",
+    "local_pref",
+    "med",
+    "communities",
+];
+
+/// Encode routes as CSV. If `fields` is set, only the
+/// listed columns are included.
+pub fn routes_to_csv(routes: &[Route], fields: Option<&str>) -> String {
+    let selected: Vec<bool> = match fields {
+        Some(fields) => {
+            let fields: HashSet<&str> =
+                fields.split(',').map(str::trim).collect();
+            CSV_COLUMNS.iter().map(|c| fields.contains(c)).collect()
+        }
+        None => vec![true; CSV_COLUMNS.len()],
+    };
+    let select = |row: [String; 8]| -> Vec<String> {
+        row.into_iter()
+            .zip(&selected)
+            .filter(|(_, selected)| **selected)
+            .map(|(field, _)| field)
+            .collect()
+    };
+
+    let mut csv = select(CSV_COLUMNS.map(String::from)).join(",");
+    csv.push('\n');
+    for route in routes {
+        let as_path: Vec<String> = route
+            .bgp
+            .as_path
+            .iter()
+            .map(|asn| asn.to_string())
+            .collect();
+        let communities: Vec<String> = route
+            .bgp
+            .communities
+            .iter()
+            .map(|c| format!("{}:{}", c.0, c.1))
+            .collect();
+        let fields = [
+            route.network.clone(),
+            route.neighbor_id.clone().unwrap_or_default(),
+            route.gateway.clone(),
+            as_path.join(" "),
+            route.bgp.origin.clone(),
+            optional_field(route.bgp.local_pref),
+            optional_field(route.bgp.med),
+            communities.join(" "),
+        ];
+        let fields: Vec<String> =
+            select(fields).iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AsPathSegment, Community, ENVELOPE_VERSION};
+
+    #[test]
+    fn test_routes_to_csv() {
+        let mut route = Route {
+            network: "192.44.19.0/24".into(),
+            neighbor_id: Some("R192_175".into()),
+            gateway: "111.111.111.111".into(),
+            ..Default::default()
+        };
+        route.bgp.as_path = vec![553, 60824];
+        route.bgp.origin = "IGP".into();
+        route.bgp.local_pref = Some(100);
+        route.bgp.med = Some(0);
+        route.bgp.communities =
+            vec![Community(553, 112), Community(553, 1200)];
+
+        let csv = routes_to_csv(&[route.clone()], None);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "network,neighbor_id,gateway,as_path,origin,local_pref,med,communities",
+                "192.44.19.0/24,R192_175,111.111.111.111,553 60824,IGP,100,0,553:112 553:1200",
+            ]
+        );
+
+        let csv = routes_to_csv(&[route], Some("as_path, network,bgp"));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines, vec!["network,as_path", "192.44.19.0/24,553 60824"]);
+    }
+
+    #[test]
+    fn test_sort_routes_aspath_len() {
+        let route = |network: &str, as_path: Vec<u32>| {
+            let mut route = Route {
+                network: network.into(),
+                ..Default::default()
+            };
+            route.bgp.as_path_segments =
+                vec![AsPathSegment::Sequence(as_path)];
+            route
+        };
+        let mut routes = vec![
+            route("10.0.0.0/8", vec![1, 2, 3]),
+            route("10.1.0.0/16", vec![1]),
+            route("10.2.0.0/16", vec![1, 2]),
+            route("10.3.0.0/16", vec![3]),
+        ];
+        sort_routes(&mut routes, RouteSort::AspathLen);
+        let networks: Vec<&str> =
+            routes.iter().map(|r| r.network.as_str()).collect();
+        assert_eq!(
+            networks,
+            vec!["10.1.0.0/16", "10.3.0.0/16", "10.2.0.0/16", "10.0.0.0/8"]
+        );
+    }
+
+    #[test]
+    fn test_routes_response_fields() {
+        let mut route = Route {
+            network: "192.44.19.0/24".into(),
+            neighbor_id: Some("R192_175".into()),
+            ..Default::default()
+        };
+        route.bgp.as_path = vec![553, 60824];
+        route.bgp.communities = vec![Community(553, 112)];
+        let response = RoutesResponse {
+            routes: vec![route],
+            ..Default::default()
+        };
+
+        let body = routes_response_to_json(
+            &response,
+            Some("network,as_path,communities"),
+        )
+        .unwrap();
+        let json: Value = serde_json::from_str(&body).unwrap();
+        assert!(json.get("api").is_some());
+        assert_eq!(
+            json["routes"][0],
+            serde_json::json!({
+                "network": "192.44.19.0/24",
+                "bgp": {
+                    "as_path": [553, 60824],
+                    "communities": [[553, 112]],
+                },
+            })
+        );
+
+        let body = routes_response_to_json(&response, None).unwrap();
+        let json: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["routes"][0]["neighbor_id"], "R192_175");
+        assert_eq!(json["routes"][0]["bgp"]["origin"], "");
+    }
+
+    #[test]
+    fn test_enrich_routes() {
+        let neighbor = Neighbor {
+            id: "R192_175".into(),
+            description: "Example Peer".into(),
+            ..Default::default()
+        };
+        let neighbors: NeighborsMap =
+            [("R192_175".to_string(), neighbor)].into_iter().collect();
+        let mut routes = vec![
+            Route {
+                neighbor_id: Some("R192_175".into()),
+                ..Default::default()
+            },
+            Route {
+                neighbor_id: Some("R1".into()),
+                ..Default::default()
+            },
+        ];
+
+        enrich_routes(&mut routes, &neighbors);
+        assert_eq!(
+            routes[0].neighbor_description,
+            Some("Example Peer".into())
+        );
+        assert_eq!(routes[1].neighbor_description, None);
+
+        let json = serde_json::to_value(&routes).unwrap();
+        assert_eq!(json[0]["neighbor_description"], "Example Peer");
+        assert!(json[1].get("neighbor_description").is_none());
+    }
+
+    #[test]
+    fn test_envelope_version() {
+        let response = StatusResponse::default();
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["api"]["envelope_version"], 2);
+        assert_eq!(response.api.envelope_version, ENVELOPE_VERSION);
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("IGP"), "IGP");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("a\"b"), "\"a\"\"b\"");
+    }
+}
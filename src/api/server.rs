@@ -1,25 +1,58 @@
+use std::sync::Arc;
+
 use anyhow::Result;
-use axum::{routing::get, Router};
-use tower_http::trace::TraceLayer;
+use axum::{
+    http::{header, HeaderValue},
+    middleware,
+    routing::get,
+    Router,
+};
 use tokio::net::TcpListener;
+use tower_http::{
+    set_header::SetResponseHeaderLayer, timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
 
 use crate::{
+    api::{
+        budget::{self, TokenBucket},
+        neighbors, status, tables,
+    },
+    bird::Birdc,
     config,
-    api::{neighbors, status, tables},
 };
 
-
 /// Get the welcome message
 async fn welcome() -> String {
-    format!("lightwatcher {}", crate::version())
+    welcome_message(config::get_root_response())
 }
 
-/// Start the API http server
-pub async fn start() -> Result<()> {
-    let app = Router::new()
+/// Use the configured root response or the version banner
+fn welcome_message(root_response: Option<String>) -> String {
+    root_response
+        .unwrap_or_else(|| format!("lightwatcher {}", crate::version()))
+}
+
+/// Create the API routes
+fn router() -> Router {
+    routes().with_state(Birdc::default())
+}
+
+/// Create the API routes. The handlers query bird through
+/// the birdc passed as state.
+fn routes() -> Router<Birdc> {
+    Router::new()
         .route("/", get(welcome))
         .route("/status", get(status::retrieve))
+        .route("/status/live", get(status::retrieve))
+        .route("/neighbors", get(neighbors::list_neighbors))
         .route("/protocols/bgp", get(neighbors::list))
+        .route("/protocols/bgp/by-asn", get(neighbors::list_by_asn))
+        .route("/protocols/down", get(neighbors::list_down))
+        .route(
+            "/protocols/bgp/:neighbor_id/routes/summary",
+            get(neighbors::routes_summary),
+        )
         .route(
             "/routes/received/:neighbor_id",
             get(neighbors::list_routes_received),
@@ -32,11 +65,51 @@ pub async fn start() -> Result<()> {
             "/routes/noexport/:neighbor_id",
             get(neighbors::list_routes_noexport),
         )
+        .route(
+            "/routes/protocol/:neighbor_id/all",
+            get(neighbors::list_routes_all),
+        )
+        .route("/routes/filtered", get(tables::list_all_routes_filtered))
+        .route("/routes/from/:ip", get(tables::list_routes_from))
+        .route("/routes/origin/:asn", get(tables::list_routes_origin))
         .route("/routes/table/:table", get(tables::list_routes))
         .route(
             "/routes/table/:table/filtered",
             get(tables::list_routes_filtered),
         )
+        .route("/routes/table/:table/count", get(tables::count_routes))
+        .route(
+            "/routes/table/:table/communities",
+            get(tables::list_communities),
+        )
+        .route(
+            "/routes/table/:table/aspath/:asn",
+            get(tables::list_routes_aspath),
+        )
+        .route(
+            "/routes/table/:table/nexthop/:gateway",
+            get(tables::list_routes_nexthop),
+        )
+        .route(
+            "/routes/table/:table/since/:seconds",
+            get(tables::list_routes_since),
+        )
+        // Responses are not cached, so they are always live
+        .layer(SetResponseHeaderLayer::if_not_present(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("no-store"),
+        ))
+}
+
+/// Start the API http server
+pub async fn start() -> Result<()> {
+    let mut app = router();
+    if let Some(rps) = config::get_global_rps() {
+        let bucket = Arc::new(TokenBucket::new(rps));
+        app = app.layer(middleware::from_fn_with_state(bucket, budget::limit));
+    }
+    let app = app
+        .layer(TimeoutLayer::new(config::get_request_timeout()))
         .layer(TraceLayer::new_for_http());
 
     let listen = config::get_listen_address();
@@ -44,3 +117,142 @@ pub async fn start() -> Result<()> {
     axum::serve(listener, app).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use axum::{
+        body::{to_bytes, Body},
+        http::{Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use crate::bird::mock::MockSocket;
+
+    #[tokio::test]
+    async fn test_welcome() {
+        assert!(welcome_message(None).starts_with("lightwatcher "));
+        assert_eq!(welcome_message(Some("OK".to_string())), "OK");
+
+        let req = Request::get("/").body(Body::empty()).unwrap();
+        let res = router().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["cache-control"], "no-store");
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        async fn slow() -> &'static str {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            "done"
+        }
+
+        let app = router()
+            .route("/slow", get(slow))
+            .layer(TimeoutLayer::new(Duration::from_millis(50)));
+
+        let req = Request::get("/slow").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::REQUEST_TIMEOUT);
+
+        let req = Request::get("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_routes_from_invalid_ip() {
+        let req = Request::get("/routes/from/not-an-ip")
+            .body(Body::empty())
+            .unwrap();
+        let res = router().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_routes_origin_invalid_asn() {
+        let req = Request::get("/routes/origin/AS64500")
+            .body(Body::empty())
+            .unwrap();
+        let res = router().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_routes_invalid_sort() {
+        let paths = [
+            "/routes/received/R1",
+            "/routes/filtered/R1",
+            "/routes/noexport/R1",
+            "/routes/protocol/R1/all",
+            "/routes/filtered",
+            "/routes/from/192.0.2.1",
+            "/routes/origin/64500",
+            "/routes/table/master4",
+            "/routes/table/master4/filtered",
+            "/routes/table/master4/aspath/64500",
+            "/routes/table/master4/nexthop/192.0.2.1",
+            "/routes/table/master4/since/60",
+        ];
+        for path in paths {
+            let uri = format!("{}?sort=nope", path);
+            let req = Request::get(uri).body(Body::empty()).unwrap();
+            let res = router().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_head_requests() {
+        let reply =
+            std::fs::read_to_string("tests/birdc/show-status").unwrap();
+        let socket = MockSocket::serve(&reply);
+        let app = routes().with_state(socket.birdc());
+
+        for path in ["/", "/status", "/status/live"] {
+            let req = Request::head(path).body(Body::empty()).unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK, "HEAD {}", path);
+            assert!(res.headers().contains_key("content-type"));
+            let body = to_bytes(res.into_body(), 1024).await.unwrap();
+            assert!(body.is_empty(), "HEAD {} has a body", path);
+        }
+        assert_eq!(socket.commands(), vec!["show status\n"; 2]);
+
+        let reply =
+            std::fs::read_to_string("tests/birdc/show-route-all-protocol-R1")
+                .unwrap();
+        let socket = MockSocket::serve(&reply);
+        let app = routes().with_state(socket.birdc());
+
+        let req = Request::head("/routes/received/R1")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = to_bytes(res.into_body(), 1024).await.unwrap();
+        assert!(body.is_empty());
+        assert_eq!(socket.commands(), vec!["show route all protocol 'R1'\n"]);
+    }
+
+    #[tokio::test]
+    async fn test_global_budget() {
+        let bucket = Arc::new(TokenBucket::new(2));
+        let app = router()
+            .layer(middleware::from_fn_with_state(bucket, budget::limit));
+
+        for _ in 0..2 {
+            let req = Request::get("/").body(Body::empty()).unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        let req = Request::get("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers()["retry-after"], "1");
+    }
+}
@@ -1,18 +1,44 @@
 use anyhow::Result;
+use axum::extract::State;
 
 use crate::{
     api::{responses::StatusResponse, Error},
     bird::Birdc,
 };
 
-/// Get the current status
-pub async fn retrieve() -> Result<String, Error> {
-    let birdc = Birdc::default();
+/// Query the status from bird
+async fn fetch_status(birdc: &Birdc) -> Result<StatusResponse> {
     let status = birdc.show_status().await?;
     let response = StatusResponse {
         status,
         ..Default::default()
     };
+    Ok(response)
+}
+
+/// Get the current status
+pub async fn retrieve(State(birdc): State<Birdc>) -> Result<String, Error> {
+    let response = fetch_status(&birdc).await?;
     let body = serde_json::to_string(&response)?;
     Ok(body)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bird::mock::MockSocket;
+
+    #[tokio::test]
+    async fn test_fetch_status_live() {
+        let reply =
+            std::fs::read_to_string("tests/birdc/show-status").unwrap();
+        let socket = MockSocket::serve(&reply);
+        let birdc = socket.birdc();
+
+        let response = fetch_status(&birdc).await.unwrap();
+        assert_eq!(response.status.router_id, "111.111.111.111");
+        fetch_status(&birdc).await.unwrap();
+
+        assert_eq!(socket.commands(), vec!["show status\n"; 2]);
+    }
+}
@@ -1,38 +1,350 @@
+use std::{collections::BTreeMap, net::IpAddr};
+
 use anyhow::Result;
-use axum::extract::Path;
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Deserialize;
 
 use crate::{
-    api::{responses::RoutesResponse, Error},
+    api::{
+        responses::{
+            apply_routes_options, encode_routes, routes_to_csv,
+            CommunitiesResponse, CommunityCount, ResponseFormat,
+            RouteCountResponse, RoutesOptions,
+        },
+        Error,
+    },
     bird::{Birdc, TableID},
+    state::Route,
 };
 
+/// Query parameters for table routes
+#[derive(Deserialize, Debug, Default)]
+pub struct RoutesQuery {
+    #[serde(default)]
+    format: ResponseFormat,
+    #[serde(flatten)]
+    options: RoutesOptions,
+}
+
 /// List all routes in a table
-pub async fn list_routes(Path(table): Path<String>) -> Result<String, Error> {
-    let birdc = Birdc::default();
+pub async fn list_routes(
+    State(birdc): State<Birdc>,
+    Path(table): Path<String>,
+    Query(query): Query<RoutesQuery>,
+) -> Result<Response, Error> {
     let table = TableID::parse(&table)?;
 
+    let mut routes = birdc.show_route_all_table(&table).await?;
+    if query.format == ResponseFormat::Csv {
+        apply_routes_options(&birdc, &mut routes, &query.options).await?;
+        let fields = query.options.fields.as_deref();
+        let body = routes_to_csv(&routes, fields);
+        let headers = [(header::CONTENT_TYPE, "text/csv")];
+        return Ok((headers, body).into_response());
+    }
+
+    let body = encode_routes(&birdc, routes, &query.options).await?;
+    Ok(body.into_response())
+}
+
+/// List all filtered routes of all tables
+pub async fn list_all_routes_filtered(
+    State(birdc): State<Birdc>,
+    Query(options): Query<RoutesOptions>,
+) -> Result<String, Error> {
+    let routes = birdc.show_route_all_filtered().await?;
+
+    let body = encode_routes(&birdc, routes, &options).await?;
+    Ok(body)
+}
+
+/// Count the occurrences of each distinct community.
+/// The result is sorted by community.
+fn count_communities<'a, T>(
+    communities: impl Iterator<Item = &'a T>,
+) -> Vec<CommunityCount<T>>
+where
+    T: Ord + Clone + 'a,
+{
+    let mut counts: BTreeMap<&T, usize> = BTreeMap::new();
+    for community in communities {
+        *counts.entry(community).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(community, count)| CommunityCount {
+            community: community.clone(),
+            count,
+        })
+        .collect()
+}
+
+/// List the distinct communities of all routes in a table
+pub async fn list_communities(
+    State(birdc): State<Birdc>,
+    Path(table): Path<String>,
+) -> Result<String, Error> {
+    let table = TableID::parse(&table)?;
     let routes = birdc.show_route_all_table(&table).await?;
-    let response = RoutesResponse {
-        routes,
+
+    let communities =
+        count_communities(routes.iter().flat_map(|r| &r.bgp.communities));
+    let large_communities = count_communities(
+        routes.iter().flat_map(|r| &r.bgp.large_communities),
+    );
+
+    let response = CommunitiesResponse {
+        communities,
+        large_communities,
         ..Default::default()
     };
     let body = serde_json::to_string(&response)?;
     Ok(body)
 }
 
-/// List all routes in a table
-pub async fn list_routes_filtered(
+/// Count the routes in a table
+pub async fn count_routes(
+    State(birdc): State<Birdc>,
     Path(table): Path<String>,
 ) -> Result<String, Error> {
-    let birdc = Birdc::default();
     let table = TableID::parse(&table)?;
-    let routes = birdc.show_route_all_filtered_table(&table).await?;
+    let count = birdc.show_route_count_table(&table).await?;
 
-    let response = RoutesResponse {
-        routes,
+    let response = RouteCountResponse {
+        count,
         ..Default::default()
     };
     let body = serde_json::to_string(&response)?;
     Ok(body)
 }
 
+/// List all routes in a table traversing an AS
+pub async fn list_routes_aspath(
+    State(birdc): State<Birdc>,
+    Path((table, asn)): Path<(String, u32)>,
+    Query(options): Query<RoutesOptions>,
+) -> Result<String, Error> {
+    let table = TableID::parse(&table)?;
+    let routes = birdc.show_route_all_table_aspath(&table, asn).await?;
+
+    let body = encode_routes(&birdc, routes, &options).await?;
+    Ok(body)
+}
+
+/// Keep only the routes using a gateway in any of their next hops
+fn filter_gateway(routes: Vec<Route>, gateway: &IpAddr) -> Vec<Route> {
+    routes
+        .into_iter()
+        .filter(|route| {
+            route.next_hops.iter().any(|hop| {
+                hop.gateway.parse::<IpAddr>().ok() == Some(*gateway)
+            })
+        })
+        .collect()
+}
+
+/// List all routes in a table using a next hop gateway
+pub async fn list_routes_nexthop(
+    State(birdc): State<Birdc>,
+    Path((table, gateway)): Path<(String, IpAddr)>,
+    Query(options): Query<RoutesOptions>,
+) -> Result<String, Error> {
+    let table = TableID::parse(&table)?;
+    let routes = birdc.show_route_all_table(&table).await?;
+    let routes = filter_gateway(routes, &gateway);
+
+    let body = encode_routes(&birdc, routes, &options).await?;
+    Ok(body)
+}
+
+/// Keep only the routes which changed at or after a point in time.
+/// Routes without a known age are dropped.
+fn filter_age(routes: Vec<Route>, since: DateTime<Utc>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .filter(|route| route.age_timestamp.is_some_and(|t| t >= since))
+        .collect()
+}
+
+/// List all routes in a table which changed recently
+pub async fn list_routes_since(
+    State(birdc): State<Birdc>,
+    Path((table, seconds)): Path<(String, u64)>,
+    Query(options): Query<RoutesOptions>,
+) -> Result<String, Error> {
+    let table = TableID::parse(&table)?;
+    let routes = birdc.show_route_all_table(&table).await?;
+    let since = TimeDelta::try_seconds(seconds.try_into().unwrap_or(i64::MAX))
+        .and_then(|age| Utc::now().checked_sub_signed(age))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    let routes = filter_age(routes, since);
+
+    let body = encode_routes(&birdc, routes, &options).await?;
+    Ok(body)
+}
+
+/// List all routes received from a source address
+pub async fn list_routes_from(
+    State(birdc): State<Birdc>,
+    Path(from): Path<IpAddr>,
+    Query(options): Query<RoutesOptions>,
+) -> Result<String, Error> {
+    let routes = birdc.show_route_all_from(&from).await?;
+
+    let body = encode_routes(&birdc, routes, &options).await?;
+    Ok(body)
+}
+
+/// List all routes originated by an AS in any table
+pub async fn list_routes_origin(
+    State(birdc): State<Birdc>,
+    Path(asn): Path<u32>,
+    Query(options): Query<RoutesOptions>,
+) -> Result<String, Error> {
+    let routes = birdc.show_route_all_origin(asn).await?;
+
+    let body = encode_routes(&birdc, routes, &options).await?;
+    Ok(body)
+}
+
+/// List all routes in a table
+pub async fn list_routes_filtered(
+    State(birdc): State<Birdc>,
+    Path(table): Path<String>,
+    Query(options): Query<RoutesOptions>,
+) -> Result<String, Error> {
+    let table = TableID::parse(&table)?;
+    let routes = birdc.show_route_all_filtered_table(&table).await?;
+
+    let body = encode_routes(&birdc, routes, &options).await?;
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::responses::RouteSort,
+        parsers::{
+            parser::{BlockIterator, Parse},
+            routes::{PrefixGroup, RE_ROUTES_START},
+        },
+        state::{Community, LargeCommunity},
+    };
+    use std::{fs::File, io::BufReader};
+
+    fn read_routes() -> Vec<Route> {
+        read_routes_file("tests/birdc/show-route-all-protocol-R1")
+    }
+
+    fn read_routes_file(path: &str) -> Vec<Route> {
+        let file = File::open(path).unwrap();
+        let reader = BufReader::new(file);
+        BlockIterator::new(reader, &RE_ROUTES_START)
+            .flat_map(|block| PrefixGroup::parse(block).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_routes_query() {
+        let uri = "/routes/table/t?format=csv&sort=aspath_len&fields=network"
+            .parse()
+            .unwrap();
+        let Query(query) = Query::<RoutesQuery>::try_from_uri(&uri).unwrap();
+        assert_eq!(query.format, ResponseFormat::Csv);
+        assert_eq!(query.options.sort, Some(RouteSort::AspathLen));
+        assert_eq!(query.options.fields, Some("network".into()));
+        assert!(!query.options.enrich);
+
+        let uri = "/routes/table/t?enrich=true".parse().unwrap();
+        let Query(query) = Query::<RoutesQuery>::try_from_uri(&uri).unwrap();
+        assert!(query.options.enrich);
+    }
+
+    #[test]
+    fn test_filter_gateway() {
+        let mut routes = read_routes();
+        routes[0].next_hops[0].gateway = "2001:db8::1".into();
+
+        let gateway = "111.111.111.111".parse().unwrap();
+        assert_eq!(filter_gateway(routes.clone(), &gateway).len(), 193);
+
+        let gateway = "2001:0db8:0::1".parse().unwrap();
+        let filtered = filter_gateway(routes.clone(), &gateway);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].network, routes[0].network);
+
+        let gateway = "192.0.2.1".parse().unwrap();
+        assert!(filter_gateway(routes, &gateway).is_empty());
+
+        // Any next hop of a multipath route matches
+        let routes = read_routes_file("tests/birdc/show-route-all-ecmp");
+        for gateway in ["111.111.111.1", "111.111.111.2"] {
+            let gateway = gateway.parse().unwrap();
+            let filtered = filter_gateway(routes.clone(), &gateway);
+            assert_eq!(filtered.len(), 1);
+            assert_eq!(filtered[0].network, "192.0.2.0/24");
+        }
+    }
+
+    #[test]
+    fn test_filter_age() {
+        let now = Utc::now();
+        let routes: Vec<Route> = [Some(30), Some(600), None, Some(60)]
+            .into_iter()
+            .map(|age| Route {
+                age_timestamp: age.map(|age| now - TimeDelta::seconds(age)),
+                ..Default::default()
+            })
+            .collect();
+
+        let since = |seconds| now - TimeDelta::seconds(seconds);
+        let ages: Vec<i64> = filter_age(routes.clone(), since(60))
+            .iter()
+            .map(|r| (now - r.age_timestamp.unwrap()).num_seconds())
+            .collect();
+        assert_eq!(ages, vec![30, 60]);
+        assert_eq!(filter_age(routes.clone(), now).len(), 0);
+        // Routes without an age are never included
+        assert_eq!(filter_age(routes, DateTime::<Utc>::MIN_UTC).len(), 3);
+    }
+
+    #[test]
+    fn test_count_communities() {
+        let routes = read_routes();
+        assert_eq!(routes.len(), 194);
+
+        let communities =
+            count_communities(routes.iter().flat_map(|r| &r.bgp.communities));
+        let count = |c: Community| {
+            communities
+                .iter()
+                .find(|cc| cc.community == c)
+                .unwrap()
+                .count
+        };
+        assert_eq!(count(Community(553, 112)), 95);
+        assert_eq!(count(Community(65101, 1001)), 194);
+
+        // Sorted and distinct
+        assert!(communities
+            .windows(2)
+            .all(|w| w[0].community < w[1].community));
+
+        let large_communities = count_communities(
+            routes.iter().flat_map(|r| &r.bgp.large_communities),
+        );
+        assert_eq!(
+            large_communities[0],
+            CommunityCount {
+                community: LargeCommunity(6695, 1000, 1),
+                count: 147,
+            }
+        );
+    }
+}
@@ -1,12 +1,14 @@
 use std::{
     fmt::Display,
-    io::{BufReader, Write},
+    io::{BufRead, BufReader, Write},
+    net::IpAddr,
     os::unix::net::UnixStream,
+    sync::Mutex,
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
 use lazy_static::lazy_static;
-use regex::Regex;
 use thiserror::Error;
 use tokio::task;
 
@@ -14,16 +16,19 @@ use crate::{
     config,
     parsers::{
         neighbors::NeighborReader,
-        parser::{BlockIterator, Parse},
-        routes::RE_ROUTES_START,
+        parser::{read_reply, Block, BlockIterator, Parse},
+        routes::{parse_table_header, RE_ROUTES_START},
         routes_worker::RoutesWorkerPool,
     },
-    state::{BirdStatus, Neighbor, NeighborsMap, Route},
+    state::{BirdStatus, Neighbor, NeighborsMap, Route, RouteCount},
 };
 
 lazy_static! {
-    /// Regex for start neighbor
-    static ref RE_STATUS_START: Regex = Regex::new(r"\d\d\d\d\s").unwrap();
+    /// Circuit breaker for all connections to bird
+    static ref BREAKER: CircuitBreaker = CircuitBreaker::new(
+        config::get_bird_breaker_threshold(),
+        config::get_bird_breaker_cooldown(),
+    );
 }
 
 #[derive(Error, Debug)]
@@ -38,6 +43,75 @@ impl Display for ValidationError {
     }
 }
 
+/// The requested object does not exist in bird
+#[derive(Error, Debug)]
+#[error("{0} not found")]
+pub struct NotFoundError(pub String);
+
+/// Bird is considered unavailable and no connection
+/// was attempted.
+#[derive(Error, Debug)]
+#[error("bird is unavailable")]
+pub struct UnavailableError;
+
+/// A circuit breaker stops connecting to bird after a number
+/// of consecutive failures. Once the cooldown has passed, a
+/// probe connection is allowed.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    failures: u32,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Create a new circuit breaker. A threshold of 0
+    /// disables the breaker.
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Check if a connection may be attempted. While the
+    /// breaker is open, this fails with an UnavailableError.
+    pub fn check(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(opened_at) = state.opened_at {
+            if opened_at.elapsed() < self.cooldown {
+                return Err(UnavailableError.into());
+            }
+            // Allow a single probe until the next cooldown
+            state.opened_at = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    /// Record a successful connection and close the breaker
+    pub fn success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures = 0;
+        state.opened_at = None;
+    }
+
+    /// Record a failed connection. The breaker opens when
+    /// the threshold is reached.
+    pub fn failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures += 1;
+        if self.threshold > 0 && state.failures >= self.threshold {
+            state.opened_at = Some(Instant::now());
+        }
+    }
+}
+
 // Validation helpers
 
 /// Basic string validation
@@ -58,8 +132,10 @@ fn validate_string(s: &str) -> Result<()> {
         .into());
     }
 
-    // Only allow [a-zA-Z0-9_]
-    if !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+    // Only allow [a-zA-Z0-9_-]. Quoted symbols in the bird
+    // config may contain hyphens, e.g. 'pb-0232'.
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    if !s.chars().all(valid) {
         return Err(ValidationError {
             input: s.to_string(),
             reason: "contains invalid characters".to_string(),
@@ -123,6 +199,7 @@ impl Display for ProtocolID {
     }
 }
 
+#[derive(Clone)]
 pub struct Birdc {
     socket: String,
 }
@@ -141,43 +218,81 @@ impl Birdc {
         Self { socket }
     }
 
+    /// Connect to the bird control socket
+    fn connect(&self) -> Result<UnixStream> {
+        BREAKER.check()?;
+        match UnixStream::connect(&self.socket) {
+            Ok(stream) => {
+                BREAKER.success();
+                Ok(stream)
+            }
+            Err(err) => {
+                BREAKER.failure();
+                Err(err.into())
+            }
+        }
+    }
+
     /// Get the daemon status.
     pub async fn show_status(&self) -> Result<BirdStatus> {
-        let mut stream = UnixStream::connect(&self.socket)?;
+        let mut stream = self.connect()?;
 
-        let cmd = format!("show status\n");
-        stream.write_all(&cmd.as_bytes())?;
+        let cmd = "show status\n";
+        stream.write_all(cmd.as_bytes())?;
 
         let reader = BufReader::new(stream);
-        let mut iter = BlockIterator::new(reader, &RE_STATUS_START);
-        let block = iter.next().unwrap();
+        let block = read_reply(reader)?;
         let status = BirdStatus::parse(block)?;
 
         Ok(status)
     }
 
-    /// Get neighbors
-    pub async fn show_protocols_all(&self) -> Result<NeighborsMap> {
-        let mut stream = UnixStream::connect(&self.socket)?;
-        let cmd = format!("show protocols all\n");
-        stream.write_all(&cmd.as_bytes())?;
+    /// Get a list of all neighbors
+    pub async fn show_neighbors(&self) -> Result<Vec<Neighbor>> {
+        let mut stream = self.connect()?;
+        let cmd = "show protocols all\n";
+        stream.write_all(cmd.as_bytes())?;
 
         let buf = BufReader::new(stream);
         let reader = NeighborReader::new(buf);
         let neighbors: Vec<Neighbor> =
             reader.filter(|n| !n.id.is_empty()).collect();
 
+        Ok(neighbors)
+    }
+
+    /// Get neighbors
+    pub async fn show_protocols_all(&self) -> Result<NeighborsMap> {
+        let neighbors = self.show_neighbors().await?;
         let neighbors: NeighborsMap =
             neighbors.into_iter().map(|n| (n.id.clone(), n)).collect();
 
         Ok(neighbors)
     }
 
+    /// Get a single neighbor
+    pub async fn show_protocol(
+        &self,
+        protocol: &ProtocolID,
+    ) -> Result<Neighbor> {
+        let mut stream = self.connect()?;
+        let cmd = format!("show protocols all '{}'\n", protocol);
+        stream.write_all(cmd.as_bytes())?;
+
+        let buf = BufReader::new(stream);
+        let mut reader = NeighborReader::new(buf);
+        let neighbor = reader
+            .find(|n| n.id == protocol.as_str())
+            .ok_or_else(|| NotFoundError(format!("protocol {}", protocol)))?;
+
+        Ok(neighbor)
+    }
+
     /// Send the command to the birdc socket and parse the response.
     /// Please note that only show route commands can be used here.
     async fn fetch_routes_cmd(&self, cmd: &str) -> Result<Vec<Route>> {
-        let mut stream = UnixStream::connect(&self.socket)?;
-        stream.write_all(&cmd.as_bytes())?;
+        let mut stream = self.connect()?;
+        stream.write_all(cmd.as_bytes())?;
         let buf = BufReader::new(stream);
 
         let blocks = BlockIterator::new(buf, &RE_ROUTES_START);
@@ -186,8 +301,16 @@ impl Birdc {
         // Spawn workers and fill queue
         let (blocks_tx, mut results_rx) = RoutesWorkerPool::spawn();
         task::spawn_blocking(move || {
+            let mut table = None;
             for block in blocks {
-                blocks_tx.send(block).unwrap();
+                if let Some(header) = block.first() {
+                    if let Some(name) = parse_table_header(header) {
+                        table = Some(name);
+                    }
+                }
+                if blocks_tx.send((table.clone(), block)).is_err() {
+                    break; // The workers are gone
+                }
             }
         });
 
@@ -210,13 +333,81 @@ impl Birdc {
         Ok(routes)
     }
 
+    /// Get the number of routes in a table without
+    /// transferring the routes.
+    pub async fn show_route_count_table(
+        &self,
+        table: &TableID,
+    ) -> Result<RouteCount> {
+        let mut stream = self.connect()?;
+        let cmd = format!("show route table '{}' count\n", table);
+        stream.write_all(cmd.as_bytes())?;
+
+        // Read until the summary or an error was received
+        let reader = BufReader::new(stream);
+        let mut block = Block::new();
+        for line in reader.lines() {
+            let line = line?;
+            let done = line.starts_with("0014")
+                || line.starts_with('8')
+                || line.starts_with('9');
+            block.push(line);
+            if done {
+                break;
+            }
+        }
+
+        let count = RouteCount::parse(block)?;
+        Ok(count)
+    }
+
+    /// Get all filtered routes of all tables
+    pub async fn show_route_all_filtered(&self) -> Result<Vec<Route>> {
+        let cmd = "show route all filtered\n";
+        let mut routes = self.fetch_routes_cmd(cmd).await?;
+        mark_filtered(&mut routes);
+        Ok(routes)
+    }
+
+    /// Get routes for a table traversing an AS
+    pub async fn show_route_all_table_aspath(
+        &self,
+        table: &TableID,
+        asn: u32,
+    ) -> Result<Vec<Route>> {
+        let cmd = format!(
+            "show route all table '{}' where bgp_path ~ [= * {} * =]\n",
+            table, asn
+        );
+        let routes = self.fetch_routes_cmd(&cmd).await?;
+        Ok(routes)
+    }
+
+    /// Get all routes received from a source address
+    pub async fn show_route_all_from(
+        &self,
+        from: &IpAddr,
+    ) -> Result<Vec<Route>> {
+        let cmd = format!("show route all where from = {}\n", from);
+        let routes = self.fetch_routes_cmd(&cmd).await?;
+        Ok(routes)
+    }
+
+    /// Get all routes originated by an AS in any table
+    pub async fn show_route_all_origin(&self, asn: u32) -> Result<Vec<Route>> {
+        let cmd = format!("show route all where bgp_path.last = {}\n", asn);
+        let routes = self.fetch_routes_cmd(&cmd).await?;
+        Ok(routes)
+    }
+
     /// Get filtered routes for a table
     pub async fn show_route_all_filtered_table(
         &self,
         table: &TableID,
     ) -> Result<Vec<Route>> {
         let cmd = format!("show route all filtered table '{}'\n", table);
-        let routes = self.fetch_routes_cmd(&cmd).await?;
+        let mut routes = self.fetch_routes_cmd(&cmd).await?;
+        mark_filtered(&mut routes);
         Ok(routes)
     }
 
@@ -236,7 +427,22 @@ impl Birdc {
         protocol: &ProtocolID,
     ) -> Result<Vec<Route>> {
         let cmd = format!("show route all filtered protocol '{}'\n", protocol);
-        let routes = self.fetch_routes_cmd(&cmd).await?;
+        let mut routes = self.fetch_routes_cmd(&cmd).await?;
+        mark_filtered(&mut routes);
+        Ok(routes)
+    }
+
+    /// Get received and filtered routes for a neighbor.
+    /// Filtered routes are marked as such.
+    pub async fn show_route_all_protocol_with_filtered(
+        &self,
+        protocol: &ProtocolID,
+    ) -> Result<Vec<Route>> {
+        let (mut routes, mut filtered) = tokio::try_join!(
+            self.show_route_all_protocol(protocol),
+            self.show_route_all_filtered_protocol(protocol),
+        )?;
+        routes.append(&mut filtered);
         Ok(routes)
     }
 
@@ -252,9 +458,90 @@ impl Birdc {
     }
 }
 
+/// Mark routes rejected by a filter. They are neither
+/// active nor selected.
+fn mark_filtered(routes: &mut [Route]) {
+    for route in routes.iter_mut() {
+        route.filtered = true;
+        route.active = false;
+        route.primary = false;
+    }
+}
+
+/// A fake bird control socket for testing. Every connection
+/// receives the same reply, the commands are recorded.
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        os::unix::net::UnixListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+    };
+
+    use super::Birdc;
+
+    static SOCKET_ID: AtomicUsize = AtomicUsize::new(0);
+
+    pub struct MockSocket {
+        path: String,
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockSocket {
+        /// Listen on a new socket and reply to all commands
+        pub fn serve(reply: &str) -> Self {
+            let id = SOCKET_ID.fetch_add(1, Ordering::SeqCst);
+            let name =
+                format!("lightwatcher-{}-{}.ctl", std::process::id(), id);
+            let path = std::env::temp_dir()
+                .join(name)
+                .to_string_lossy()
+                .to_string();
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).unwrap();
+
+            let commands = Arc::new(Mutex::new(vec![]));
+            let received = commands.clone();
+            let reply = reply.to_string();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut cmd = String::new();
+                    let mut reader = BufReader::new(&stream);
+                    reader.read_line(&mut cmd).unwrap();
+                    received.lock().unwrap().push(cmd);
+                    stream.write_all(reply.as_bytes()).unwrap();
+                }
+            });
+
+            Self { path, commands }
+        }
+
+        /// Create a birdc client connecting to the socket
+        pub fn birdc(&self) -> Birdc {
+            Birdc::new(self.path.clone())
+        }
+
+        /// Get all commands received
+        pub fn commands(&self) -> Vec<String> {
+            self.commands.lock().unwrap().clone()
+        }
+    }
+
+    impl Drop for MockSocket {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use super::{mock::MockSocket, *};
 
     #[test]
     fn test_table_id() {
@@ -280,5 +567,254 @@ mod tests {
 
         let result = ProtocolID::parse("R192`date`175");
         assert!(result.is_err());
+
+        let protocol = ProtocolID::parse("pb-0232").unwrap();
+        assert_eq!(protocol.as_str(), "pb-0232");
+
+        for id in ["pb-0232;reboot", "pb-$(id)", "pb-0232|cat", "pb-'"] {
+            assert!(ProtocolID::parse(id).is_err(), "{}", id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_show_protocol_hyphen() {
+        let reply = "0001 BIRD 2.0.10 ready.
+2002-Name       Proto      Table      State  Since         Info
+1002-pb-0232    BGP        ---        up     2023-04-19 09:22:02  Established
+1006-  Description:    AS112
+   BGP state:          Established
+     Neighbor AS:      112
+0000 
+";
+        let socket = MockSocket::serve(reply);
+        let birdc = socket.birdc();
+        let protocol = ProtocolID::parse("pb-0232").unwrap();
+        let neighbor = birdc.show_protocol(&protocol).await.unwrap();
+        assert_eq!(neighbor.id, "pb-0232");
+        assert_eq!(neighbor.state, "up");
+        assert_eq!(neighbor.asn, 112);
+    }
+
+    #[tokio::test]
+    async fn test_show_status() {
+        let reply =
+            std::fs::read_to_string("tests/birdc/show-status").unwrap();
+        let socket = MockSocket::serve(&reply);
+        let status = socket.birdc().show_status().await.unwrap();
+        assert_eq!(status.version, "2.0.10");
+        assert_eq!(status.router_id, "111.111.111.111");
+        assert_eq!(status.message, "Daemon is up and running");
+
+        let reply =
+            std::fs::read_to_string("tests/birdc/show-status-shutdown")
+                .unwrap();
+        let socket = MockSocket::serve(&reply);
+        let status = socket.birdc().show_status().await.unwrap();
+        assert_eq!(
+            status.message,
+            "Shutdown in progress\nGraceful restart requested\nby administrator"
+        );
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+        assert!(breaker.check().is_ok());
+
+        breaker.failure();
+        assert!(breaker.check().is_ok());
+        breaker.failure();
+        let err = breaker.check().unwrap_err();
+        assert!(err.is::<UnavailableError>());
+
+        // Allow a single probe after the cooldown
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.check().is_ok());
+        assert!(breaker.check().is_err());
+
+        breaker.success();
+        assert!(breaker.check().is_ok());
+    }
+
+    #[test]
+    fn test_circuit_breaker_probe_failure() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(50));
+        breaker.failure();
+        assert!(breaker.check().is_err());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.check().is_ok());
+        breaker.failure();
+        assert!(breaker.check().is_err());
+    }
+
+    #[test]
+    fn test_circuit_breaker_disabled() {
+        let breaker = CircuitBreaker::new(0, Duration::from_secs(10));
+        for _ in 0..10 {
+            breaker.failure();
+        }
+        assert!(breaker.check().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_show_route_all_protocol_with_filtered() {
+        let reply = "0001 BIRD 2.0.10 ready.
+1007-10.0.0.0/8  unicast [R1 2023-04-19 09:35:57] * (100) [AS1i]
+ \tvia 172.31.193.103 on vx0
+1008-\tType: BGP univ
+0000 
+";
+        let socket = MockSocket::serve(reply);
+        let birdc = socket.birdc();
+        let protocol = ProtocolID::parse("R1").unwrap();
+        let routes = birdc
+            .show_route_all_protocol_with_filtered(&protocol)
+            .await
+            .unwrap();
+
+        assert_eq!(routes.len(), 2);
+        assert!(!routes[0].filtered);
+        assert!(routes[0].active);
+        assert!(routes[0].primary);
+        assert!(routes[1].filtered);
+        assert!(!routes[1].active);
+        assert!(!routes[1].primary);
+
+        let mut commands = socket.commands();
+        commands.sort();
+        assert_eq!(
+            commands,
+            vec![
+                "show route all filtered protocol 'R1'\n",
+                "show route all protocol 'R1'\n",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_show_neighbors() {
+        let reply =
+            std::fs::read_to_string("tests/birdc/show-protocols-all").unwrap();
+        let socket = MockSocket::serve(&reply);
+        let birdc = socket.birdc();
+        let neighbors = birdc.show_neighbors().await.unwrap();
+
+        let ids: Vec<&str> = neighbors.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "R194_42", "R195_42", "R195_77", "R_pp_60", "R_pp_75",
+                "R_pp_90"
+            ]
+        );
+        assert_eq!(neighbors[0].asn, 42);
+        assert_eq!(socket.commands(), vec!["show protocols all\n"]);
+    }
+
+    #[tokio::test]
+    async fn test_show_route_count_table() {
+        let reply = std::fs::read_to_string(
+            "tests/birdc/show-route-table-master4-count",
+        )
+        .unwrap();
+        let socket = MockSocket::serve(&reply);
+        let birdc = socket.birdc();
+        let table = TableID::parse("master4").unwrap();
+        let count = birdc.show_route_count_table(&table).await.unwrap();
+        assert_eq!(count.routes, 712337);
+        assert_eq!(
+            socket.commands(),
+            vec!["show route table 'master4' count\n"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_show_route_all_filtered() {
+        let socket = MockSocket::serve("0001 BIRD 2.0.10 ready.\n0000 \n");
+        let birdc = socket.birdc();
+        birdc.show_route_all_filtered().await.unwrap();
+        assert_eq!(socket.commands(), vec!["show route all filtered\n"]);
+    }
+
+    #[tokio::test]
+    async fn test_show_route_all_table_aspath() {
+        let socket = MockSocket::serve("0001 BIRD 2.0.10 ready.\n0000 \n");
+        let birdc = socket.birdc();
+        let table = TableID::parse("master4").unwrap();
+        let routes = birdc
+            .show_route_all_table_aspath(&table, 64500)
+            .await
+            .unwrap();
+        assert!(routes.is_empty());
+
+        assert_eq!(
+            socket.commands(),
+            vec![
+                "show route all table 'master4' where bgp_path ~ [= * 64500 * =]\n"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_show_route_all_from() {
+        let socket = MockSocket::serve("0001 BIRD 2.0.10 ready.\n0000 \n");
+        let birdc = socket.birdc();
+        let v4: IpAddr = "192.0.2.1".parse().unwrap();
+        let v6: IpAddr = "2001:db8::1".parse().unwrap();
+        birdc.show_route_all_from(&v4).await.unwrap();
+        birdc.show_route_all_from(&v6).await.unwrap();
+
+        assert_eq!(
+            socket.commands(),
+            vec![
+                "show route all where from = 192.0.2.1\n",
+                "show route all where from = 2001:db8::1\n",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_routes_tables() {
+        let reply =
+            std::fs::read_to_string("tests/birdc/show-route-all-two-tables")
+                .unwrap();
+        let socket = MockSocket::serve(&reply);
+        let birdc = socket.birdc();
+        let mut routes = birdc.show_route_all_filtered().await.unwrap();
+        routes.sort_by(|a, b| a.network.cmp(&b.network));
+
+        let tables: Vec<(&str, Option<&str>)> = routes
+            .iter()
+            .map(|r| (r.network.as_str(), r.table.as_deref()))
+            .collect();
+        assert_eq!(
+            tables,
+            vec![
+                ("192.0.2.0/24", Some("master4")),
+                ("198.51.100.0/24", Some("master4")),
+                ("2001:db8::/32", Some("master6")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_show_route_all_origin() {
+        let reply =
+            std::fs::read_to_string("tests/birdc/show-route-all-two-tables")
+                .unwrap();
+        let socket = MockSocket::serve(&reply);
+        let birdc = socket.birdc();
+        let routes = birdc.show_route_all_origin(65001).await.unwrap();
+        assert_eq!(
+            socket.commands(),
+            vec!["show route all where bgp_path.last = 65001\n"]
+        );
+
+        let mut tables: Vec<&str> =
+            routes.iter().filter_map(|r| r.table.as_deref()).collect();
+        tables.sort();
+        tables.dedup();
+        assert_eq!(tables, vec!["master4", "master6"]);
     }
 }
@@ -1,13 +1,163 @@
+use std::{str::FromStr, time::Duration};
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+use crate::parsers::datetime::Timezone;
+
 /// Get the birdc socket path from the environment
 /// or use the default value.
 pub fn get_birdc_socket() -> String {
-    let socket = std::env::var("LIGHTWATCHER_BIRDC")
-        .unwrap_or("/var/run/bird/bird.ctl".to_string());
-    socket
+    std::env::var("LIGHTWATCHER_BIRDC")
+        .unwrap_or("/var/run/bird/bird.ctl".to_string())
 }
 
+/// Get the listen address of the API server
 pub fn get_listen_address() -> String {
-    let listen = std::env::var("LIGHTWATCHER_LISTEN")
-        .unwrap_or("127.0.0.1:8181".to_string());
-    listen
+    std::env::var("LIGHTWATCHER_LISTEN")
+        .unwrap_or("127.0.0.1:8181".to_string())
+}
+
+/// Get the maximum number of communities per route.
+/// Communities are not truncated if this is not set.
+pub fn get_max_communities_per_route() -> Option<usize> {
+    std::env::var("LIGHTWATCHER_MAX_COMMUNITIES_PER_ROUTE")
+        .ok()
+        .and_then(|max| max.parse().ok())
+}
+
+/// Get the maximum number of routes for a single prefix.
+/// Routes are not truncated if this is not set.
+pub fn get_max_routes_per_prefix() -> Option<usize> {
+    std::env::var("LIGHTWATCHER_MAX_ROUTES_PER_PREFIX")
+        .ok()
+        .and_then(|max| max.parse().ok())
+}
+
+/// Get the body of the response to `/`, e.g. for
+/// health checks of a load balancer.
+pub fn get_root_response() -> Option<String> {
+    std::env::var("LIGHTWATCHER_ROOT_RESPONSE").ok()
+}
+
+/// Get the maximum duration of a request. Slower requests
+/// are aborted. The timeout is configured in seconds.
+pub fn get_request_timeout() -> Duration {
+    let timeout = std::env::var("LIGHTWATCHER_REQUEST_TIMEOUT")
+        .ok()
+        .and_then(|timeout| timeout.parse().ok())
+        .unwrap_or(60);
+    Duration::from_secs(timeout)
+}
+
+/// Get the timezone of the timestamps printed by bird.
+/// This can be an IANA name or a fixed offset like `+02:00`.
+pub fn get_bird_timezone() -> String {
+    std::env::var("LIGHTWATCHER_BIRD_TIMEZONE").unwrap_or("UTC".to_string())
+}
+
+/// Get the number of consecutive failed connections to bird
+/// after which new requests fail immediately. 0 disables this.
+pub fn get_bird_breaker_threshold() -> u32 {
+    std::env::var("LIGHTWATCHER_BIRD_BREAKER_THRESHOLD")
+        .ok()
+        .and_then(|threshold| threshold.parse().ok())
+        .unwrap_or(5)
+}
+
+/// Get the time in seconds after which a connection to bird
+/// is attempted again.
+pub fn get_bird_breaker_cooldown() -> Duration {
+    let cooldown = std::env::var("LIGHTWATCHER_BIRD_BREAKER_COOLDOWN")
+        .ok()
+        .and_then(|cooldown| cooldown.parse().ok())
+        .unwrap_or(10);
+    Duration::from_secs(cooldown)
+}
+
+/// Check if private ASNs should be removed from AS paths.
+pub fn get_redact_private_asn() -> bool {
+    std::env::var("LIGHTWATCHER_REDACT_PRIVATE_ASN")
+        .map(|redact| redact == "true")
+        .unwrap_or(false)
+}
+
+/// Get the regex to extract the ASN from a protocol id,
+/// e.g. `as(\d+)$` for `pb_0232_as62193`.
+pub fn get_protocol_id_asn_regex() -> Option<Regex> {
+    std::env::var("LIGHTWATCHER_PROTOCOL_ID_ASN_REGEX")
+        .ok()
+        .and_then(|re| Regex::new(&re).ok())
+}
+
+/// Get the maximum number of requests per second across
+/// all clients. Requests are not limited if this is not set.
+pub fn get_global_rps() -> Option<u32> {
+    std::env::var("LIGHTWATCHER_GLOBAL_RPS")
+        .ok()
+        .and_then(|rps| rps.parse().ok())
+        .filter(|rps| *rps > 0)
+}
+
+/// Check if the configuration should only be validated
+/// without starting the server.
+pub fn get_validate_config() -> bool {
+    std::env::var("LIGHTWATCHER_VALIDATE_CONFIG")
+        .map(|validate| validate == "true")
+        .unwrap_or(false)
+}
+
+/// Check that a variable, if set, can be parsed
+fn validate_var<T: FromStr>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    key: &str,
+) -> Result<()> {
+    match lookup(key) {
+        Some(value) if value.parse::<T>().is_err() => {
+            Err(anyhow!("invalid value for {}: '{}'", key, value))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Check that all configured values are valid. Invalid values
+/// are otherwise silently replaced by their defaults.
+pub fn validate() -> Result<()> {
+    validate_with(|key| std::env::var(key).ok())
+}
+
+/// Check the values returned by the lookup for each variable
+fn validate_with(lookup: impl Fn(&str) -> Option<String>) -> Result<()> {
+    validate_var::<usize>(&lookup, "LIGHTWATCHER_MAX_COMMUNITIES_PER_ROUTE")?;
+    validate_var::<usize>(&lookup, "LIGHTWATCHER_MAX_ROUTES_PER_PREFIX")?;
+    validate_var::<u64>(&lookup, "LIGHTWATCHER_REQUEST_TIMEOUT")?;
+    validate_var::<Timezone>(&lookup, "LIGHTWATCHER_BIRD_TIMEZONE")?;
+    validate_var::<u32>(&lookup, "LIGHTWATCHER_BIRD_BREAKER_THRESHOLD")?;
+    validate_var::<u64>(&lookup, "LIGHTWATCHER_BIRD_BREAKER_COOLDOWN")?;
+    validate_var::<bool>(&lookup, "LIGHTWATCHER_REDACT_PRIVATE_ASN")?;
+    validate_var::<Regex>(&lookup, "LIGHTWATCHER_PROTOCOL_ID_ASN_REGEX")?;
+    validate_var::<u32>(&lookup, "LIGHTWATCHER_GLOBAL_RPS")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let lookup = |value: &'static str| {
+            move |key: &str| {
+                (key == "LIGHTWATCHER_BIRD_BREAKER_COOLDOWN")
+                    .then(|| value.to_string())
+            }
+        };
+
+        let err = validate_with(lookup("ten")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value for LIGHTWATCHER_BIRD_BREAKER_COOLDOWN: 'ten'"
+        );
+        assert!(validate_with(lookup("10")).is_ok());
+    }
 }
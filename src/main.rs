@@ -19,10 +19,53 @@ async fn main() -> Result<()> {
 
     // Print info
     tracing::info!(version = lightwatcher::version(), "starting service");
-    tracing::info!(LIGHTWATCHER_LISTEN = config::get_listen_address(), "env");
-    tracing::info!(LIGHTWATCHER_BIRDC = config::get_birdc_socket(), "env");
+    log_config();
+
+    if config::get_validate_config() {
+        config::validate()?;
+        tracing::info!("configuration is valid");
+        return Ok(());
+    }
 
     // Start API server
     api::server::start().await?;
     Ok(())
 }
+
+/// Log the effective configuration
+fn log_config() {
+    tracing::info!(LIGHTWATCHER_LISTEN = config::get_listen_address(), "env");
+    tracing::info!(LIGHTWATCHER_BIRDC = config::get_birdc_socket(), "env");
+    tracing::info!(
+        LIGHTWATCHER_BIRD_TIMEZONE = config::get_bird_timezone(),
+        "env"
+    );
+    tracing::info!(
+        LIGHTWATCHER_REQUEST_TIMEOUT = config::get_request_timeout().as_secs(),
+        "env"
+    );
+    tracing::info!(
+        LIGHTWATCHER_BIRD_BREAKER_THRESHOLD =
+            config::get_bird_breaker_threshold(),
+        LIGHTWATCHER_BIRD_BREAKER_COOLDOWN =
+            config::get_bird_breaker_cooldown().as_secs(),
+        "env"
+    );
+    tracing::info!(
+        LIGHTWATCHER_REDACT_PRIVATE_ASN = config::get_redact_private_asn(),
+        LIGHTWATCHER_MAX_COMMUNITIES_PER_ROUTE =
+            ?config::get_max_communities_per_route(),
+        LIGHTWATCHER_MAX_ROUTES_PER_PREFIX =
+            ?config::get_max_routes_per_prefix(),
+        "env"
+    );
+    tracing::info!(
+        LIGHTWATCHER_GLOBAL_RPS = ?config::get_global_rps(),
+        "env"
+    );
+    tracing::info!(
+        LIGHTWATCHER_PROTOCOL_ID_ASN_REGEX =
+            ?config::get_protocol_id_asn_regex().map(|re| re.to_string()),
+        "env"
+    );
+}
@@ -1,19 +1,87 @@
+use std::str::FromStr;
+
 use anyhow::Result;
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use lazy_static::lazy_static;
 use thiserror::Error;
 
+use crate::config;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Invalid date time string: '{0}'")]
     InvalidDateTimeString(String),
+    #[error("Invalid timezone: '{0}'")]
+    InvalidTimezone(String),
+}
+
+lazy_static! {
+    /// The timezone of the timestamps printed by bird
+    static ref BIRD_TIMEZONE: Timezone = {
+        let tz = config::get_bird_timezone();
+        tz.parse().unwrap_or_else(|_| {
+            tracing::warn!(timezone = tz, "invalid timezone, using UTC");
+            Timezone::Named(Tz::UTC)
+        })
+    };
+}
+
+/// A timezone is either an IANA name like `Europe/Berlin`
+/// or a fixed offset like `+02:00`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Timezone {
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl FromStr for Timezone {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(offset) = s.parse::<FixedOffset>() {
+            return Ok(Timezone::Fixed(offset));
+        }
+        s.parse::<Tz>()
+            .map(Timezone::Named)
+            .map_err(|_| Error::InvalidTimezone(s.to_string()))
+    }
 }
 
-/// Parse date time string.
-/// TODO: A timezone should be specified as a parameter.
+impl Timezone {
+    /// Get the current date in the timezone
+    fn today(&self) -> NaiveDate {
+        let now = Utc::now();
+        match self {
+            Timezone::Named(tz) => now.with_timezone(tz).date_naive(),
+            Timezone::Fixed(tz) => now.with_timezone(tz).date_naive(),
+        }
+    }
+
+    /// Interpret a local date time in the timezone
+    fn to_utc(&self, datetime: &NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Timezone::Named(tz) => tz
+                .from_local_datetime(datetime)
+                .earliest()
+                .map(|d| d.with_timezone(&Utc)),
+            Timezone::Fixed(tz) => tz
+                .from_local_datetime(datetime)
+                .earliest()
+                .map(|d| d.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// Parse date time string in the configured bird timezone.
 pub fn parse(s: &str) -> Result<DateTime<Utc>> {
+    parse_in(s, &BIRD_TIMEZONE)
+}
+
+/// Parse date time string in a timezone.
+pub fn parse_in(s: &str, tz: &Timezone) -> Result<DateTime<Utc>> {
     let parts: Vec<&str> = s.split_whitespace().collect();
-    let now = Utc::now();
-    let date = format!("{}", now.format("%Y-%m-%d"));
+    let date = format!("{}", tz.today().format("%Y-%m-%d"));
 
     let (date, time) = match parts.len() {
         1 => (date.as_ref(), parts[0]),
@@ -28,7 +96,9 @@ pub fn parse(s: &str) -> Result<DateTime<Utc>> {
     let datetime = format!("{} {}", date, time);
     let datetime =
         NaiveDateTime::parse_from_str(datetime.as_ref(), "%Y-%m-%d %H:%M:%S")?;
-    let datetime = Utc.from_utc_datetime(&datetime);
+    let datetime = tz
+        .to_utc(&datetime)
+        .ok_or_else(|| Error::InvalidDateTimeString(s.to_string()))?;
 
     Ok(datetime)
 }
@@ -36,12 +106,36 @@ pub fn parse(s: &str) -> Result<DateTime<Utc>> {
 /// Parse date time string into a duration
 pub fn parse_duration_sec(s: &str) -> Result<f64> {
     let datetime = parse(s)?;
+    Ok(duration_sec(&datetime))
+}
+
+/// Get the seconds between a date time and now
+pub fn duration_sec(datetime: &DateTime<Utc>) -> f64 {
     let now = Utc::now();
     let duration = datetime.signed_duration_since(now);
     let duration = duration.num_seconds();
-    let duration = duration.abs() as f64;
+    duration.abs() as f64
+}
 
-    Ok(duration)
+/// Parse a relative duration like `1d12:34:56` into seconds.
+/// The days are optional, fractions of seconds are ignored.
+pub fn parse_relative_duration(s: &str) -> Result<f64> {
+    let invalid = || Error::InvalidDateTimeString(s.to_string());
+    let (days, time) = match s.trim().split_once('d') {
+        Some((days, time)) => (days.parse::<u64>()?, time),
+        None => (0, s.trim()),
+    };
+    let time = time.split('.').next().unwrap_or_default();
+    let parts: Vec<u64> = time
+        .split(':')
+        .map(|part| part.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid())?;
+    let [hours, minutes, seconds] = parts[..] else {
+        return Err(invalid().into());
+    };
+    let duration = ((days * 24 + hours) * 60 + minutes) * 60 + seconds;
+    Ok(duration as f64)
 }
 
 #[cfg(test)]
@@ -72,6 +166,35 @@ mod tests {
         assert_eq!(result.hour(), 10);
     }
 
+    #[test]
+    fn test_parse_in_timezone() {
+        let tz: Timezone = "Europe/Berlin".parse().unwrap();
+        let result = parse_in("2022-06-23 10:42:11", &tz).unwrap();
+        assert_eq!(result.day(), 23);
+        assert_eq!(result.hour(), 8);
+        assert_eq!(result.minute(), 42);
+
+        let tz: Timezone = "Europe/Berlin".parse().unwrap();
+        let result = parse_in("2022-01-23 10:42:11", &tz).unwrap();
+        assert_eq!(result.hour(), 9);
+
+        let tz: Timezone = "+05:30".parse().unwrap();
+        let result = parse_in("2022-06-23 03:12:11", &tz).unwrap();
+        assert_eq!(result.day(), 22);
+        assert_eq!(result.hour(), 21);
+        assert_eq!(result.minute(), 42);
+
+        let tz: Timezone = "UTC".parse().unwrap();
+        let result = parse_in("2022-06-23 10:42:11", &tz).unwrap();
+        assert_eq!(result.hour(), 10);
+    }
+
+    #[test]
+    fn test_parse_timezone_invalid() {
+        let result = "Mars/Olympus_Mons".parse::<Timezone>();
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_parse_invalid() {
         let result = parse("2022-06-23 10:42:11 10:42:11");
@@ -90,4 +213,18 @@ mod tests {
         .unwrap();
         assert_eq!(result, 300.0);
     }
+
+    #[test]
+    fn test_parse_relative_duration() {
+        let tests = vec![
+            ("1d12:34:56", 131696.0),
+            ("0d00:00:01", 1.0),
+            ("12:34:56.123", 45296.0),
+        ];
+        for (s, seconds) in tests {
+            assert_eq!(parse_relative_duration(s).unwrap(), seconds, "{}", s);
+        }
+        assert!(parse_relative_duration("1d12:34").is_err());
+        assert!(parse_relative_duration("xd12:34:56").is_err());
+    }
 }
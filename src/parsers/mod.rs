@@ -1,5 +1,6 @@
 pub mod datetime;
 pub mod neighbors;
+pub mod route_count;
 pub mod routes;
 pub mod status;
 
@@ -1,14 +1,16 @@
 use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::io::BufRead;
 
 use crate::{
+    config,
     parsers::{
         datetime,
-        parser::{Block, BlockIterator, Parse},
+        parser::{strip_control_code, Block, BlockIterator, Parse},
     },
-    state::Neighbor,
+    state::{Channel, Neighbor},
 };
 
 lazy_static! {
@@ -17,11 +19,11 @@ lazy_static! {
 
     /// Regex: Neighbor header (protocol, state, uptime, ...)
     static ref RE_NEIGHBOR_HEADER: Regex = Regex::new(r"(?x)
-        1002-(?P<protocol>\w+)   # protocol id
-        \s+.*?\s+                # ignore this part
-        (?P<state>\w+)           # state (up / down)
+        1002-(?P<protocol>[\w-]+)  # protocol id
+        \s+.*?\s+                # proto, table, ... (ignored)
+        (?P<state>up|down|start|passive|stop|flush)  # state
         \s+
-        (?P<uptime>[\d\-:\s]+)    # since
+        (?P<uptime>[\d\-:\sd]+)   # since, may be relative
         (\.\d+)?\s+?              # trailing time
         (?P<info>.*)$             # additional info
     ").unwrap();
@@ -33,6 +35,10 @@ lazy_static! {
         \s+
         (?P<value>.+)
     ").unwrap();
+
+    /// Extract the ASN from the protocol id
+    static ref RE_PROTOCOL_ID_ASN: Option<Regex> =
+        config::get_protocol_id_asn_regex();
 }
 
 /// Parser sections
@@ -40,7 +46,18 @@ lazy_static! {
 enum State {
     Start,
     Meta,
-    BgpState,
+    Bgp,
+    Capabilities(usize),
+    RouteChangeStats,
+    /// A description, which may be wrapped across lines indented
+    /// deeper than its key. The section is resumed afterwards.
+    Description(usize, Section),
+}
+
+/// Sections which may contain the description
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Section {
+    Bgp,
     RouteChangeStats,
 }
 
@@ -79,7 +96,7 @@ impl Parse for Neighbor {
         // Parse lines in block
         let mut state = State::Start;
         for line in block.iter() {
-            match parse_line(&mut neighbor, state, &line) {
+            match parse_line(&mut neighbor, state, line) {
                 Ok(next_state) => state = next_state,
                 Err(e) => {
                     println!("Error parsing line: {}, {}", line, e);
@@ -88,21 +105,31 @@ impl Parse for Neighbor {
             }
         }
 
+        if neighbor.asn == 0 {
+            if let Some(re) = &*RE_PROTOCOL_ID_ASN {
+                neighbor.derived_asn = derive_asn(&neighbor.id, re);
+            }
+        }
+
         Ok(neighbor)
     }
 }
 
 fn parse_line(
-    mut neighbor: &mut Neighbor,
+    neighbor: &mut Neighbor,
     state: State,
     line: &str,
 ) -> Result<State> {
     let state = match state {
-        State::Start => parse_neighbor_header(&mut neighbor, line)?,
-        State::Meta => parse_neighbor_meta(&mut neighbor, line)?,
-        State::BgpState => parse_bgp_state(&mut neighbor, line)?,
-        State::RouteChangeStats => {
-            parse_route_change_stats(&mut neighbor, line)?
+        State::Start => parse_neighbor_header(neighbor, line)?,
+        State::Meta => parse_neighbor_meta(neighbor, line)?,
+        State::Bgp => parse_bgp_state(neighbor, line)?,
+        State::Capabilities(indent) => {
+            parse_capabilities(neighbor, indent, line)?
+        }
+        State::RouteChangeStats => parse_route_change_stats(neighbor, line)?,
+        State::Description(indent, section) => {
+            parse_wrapped_description(neighbor, indent, section, line)?
         }
     };
     Ok(state)
@@ -124,12 +151,22 @@ fn parse_neighbor_header(
         neighbor.id = caps["protocol"].to_string();
         // State
         neighbor.state = caps["state"].to_string().to_lowercase();
-        if neighbor.state == "down" {
+        // Sessions not established carry the reason in the info
+        let state = neighbor.state.as_str();
+        if matches!(state, "down" | "start" | "passive") {
             neighbor.last_error = caps["info"].to_string();
+            neighbor.last_error_class = classify_error(&neighbor.last_error);
+        }
+        // Uptime, either a timestamp or relative like `1d12:34:56`
+        let uptime = caps["uptime"].trim();
+        if uptime.contains('d') {
+            neighbor.uptime = datetime::parse_relative_duration(uptime)?;
+            neighbor.since =
+                Utc::now() - Duration::seconds(neighbor.uptime as i64);
+        } else {
+            neighbor.uptime = datetime::parse_duration_sec(uptime)?;
+            neighbor.since = datetime::parse(uptime)?;
         }
-        // Uptime
-        neighbor.uptime = datetime::parse_duration_sec(&caps["uptime"])?;
-        neighbor.since = datetime::parse(&caps["uptime"])?;
 
         State::Meta
     } else {
@@ -139,35 +176,208 @@ fn parse_neighbor_header(
     Ok(next_state)
 }
 
+/// Extract the ASN from a protocol id. The ASN is the
+/// capture group `asn` or else the first group.
+fn derive_asn(id: &str, re: &Regex) -> Option<u32> {
+    let caps = re.captures(id)?;
+    let asn = caps.name("asn").or_else(|| caps.get(1))?;
+    asn.as_str().parse().ok()
+}
+
+/// Classify an error message by known substrings
+fn classify_error(error: &str) -> Option<String> {
+    let error = error.to_lowercase();
+    let class = if error.contains("bad peer as") {
+        "peer_as"
+    } else if error.contains("hold timer expired") {
+        "hold_timer"
+    } else if error.contains("route limit exceeded") {
+        "route_limit"
+    } else if error.contains("administrative shutdown") {
+        "admin_shutdown"
+    } else if error.contains("no listening socket")
+        || error.contains("connection refused")
+        || error.contains("connection reset")
+        || error.contains("connection closed")
+        || error.contains("connection lost")
+    {
+        "connection"
+    } else if error.contains("error") {
+        "other"
+    } else {
+        return None;
+    };
+    Some(class.to_string())
+}
+
+/// Parse the description and return the indentation of its line.
+/// Depending on the layout, it is printed before the BGP state
+/// or at the end of the protocol.
+fn parse_description(neighbor: &mut Neighbor, line: &str) -> Option<usize> {
+    let caps = RE_KEY_VALUE.captures(line)?;
+    let key = caps.name("key")?;
+    if !key.as_str().trim().eq_ignore_ascii_case("description") {
+        return None;
+    }
+    neighbor.description = caps["value"].to_string();
+    Some(indentation(line))
+}
+
+/// Append the lines of a wrapped description. The first line
+/// not indented deeper than the key continues the section.
+fn parse_wrapped_description(
+    neighbor: &mut Neighbor,
+    indent: usize,
+    section: Section,
+    line: &str,
+) -> Result<State> {
+    if indentation(line) > indent && !line.trim().is_empty() {
+        neighbor.description.push(' ');
+        neighbor.description.push_str(line.trim());
+        return Ok(State::Description(indent, section));
+    }
+    match section {
+        Section::Bgp => parse_bgp_state(neighbor, line),
+        Section::RouteChangeStats => parse_route_change_stats(neighbor, line),
+    }
+}
+
 /// Parse neighbor meta: Description,
 fn parse_neighbor_meta(neighbor: &mut Neighbor, line: &str) -> Result<State> {
-    // Parse description
-    let caps = RE_KEY_VALUE.captures(line);
-    if let Some(caps) = caps {
-        neighbor.description = caps["value"].to_string();
+    if let Some(indent) = parse_description(neighbor, line) {
+        return Ok(State::Description(indent, Section::Bgp));
     }
-
-    Ok(State::BgpState)
+    if RE_KEY_VALUE.is_match(line) {
+        // The BGP state section starts
+        return parse_bgp_state(neighbor, line);
+    }
+    Ok(State::Bgp)
 }
 
 /// ParseBGP State
 fn parse_bgp_state(neighbor: &mut Neighbor, line: &str) -> Result<State> {
+    if line.trim() == "Neighbor capabilities" {
+        return Ok(State::Capabilities(indentation(line)));
+    }
+
+    if let Some(indent) = parse_description(neighbor, line) {
+        return Ok(State::Description(indent, Section::Bgp));
+    }
+
+    // Each channel starts with its name, e.g. `Channel ipv4`
+    if let Some(name) = line.trim().strip_prefix("Channel ") {
+        neighbor.channels.push(Channel {
+            name: name.trim().to_string(),
+            ..Default::default()
+        });
+        return Ok(State::Bgp);
+    }
+
     // This is a collection of key value pairs.
     if let Some(caps) = RE_KEY_VALUE.captures(line) {
         let key = caps["key"].to_lowercase();
         let val = caps["value"].to_string();
 
-        if key == "neighbor address" {
+        if key == "bgp state" {
+            neighbor.bgp_state = val.trim().to_lowercase();
+        } else if key == "neighbor address" {
             neighbor.address = val
         } else if key == "neighbor as" {
             neighbor.asn = val.parse::<u32>()?;
+        } else if key == "session" || key == "bgp session" {
+            // e.g. `external route-server AS4`
+            neighbor.session_type =
+                val.split_whitespace().next().map(|t| t.to_lowercase());
+        } else if key == "last error" {
+            // More detailed than the info in the header
+            neighbor.last_error = val.trim().to_string();
+            neighbor.last_error_class = classify_error(&neighbor.last_error);
         } else if key == "route change stats" {
             // We found the next segment
             return Ok(State::RouteChangeStats);
+        } else if let Some(channel) = neighbor.channels.last_mut() {
+            parse_channel_meta(channel, &key, &val);
+        }
+    } else if let Some(gr) = line.trim().strip_prefix("GR ") {
+        // Graceful restart in progress, e.g. 'GR wait'
+        if let Some(channel) = neighbor.channels.last_mut() {
+            channel.gr_state = Some(gr.trim().to_lowercase());
         }
     }
 
-    Ok(State::BgpState)
+    Ok(State::Bgp)
+}
+
+/// Parse a key value pair of the current channel
+fn parse_channel_meta(channel: &mut Channel, key: &str, val: &str) {
+    if key == "state" {
+        channel.state = val.trim().to_lowercase();
+    } else if key == "table" {
+        channel.table = parse_table(val);
+    } else if key == "receive limit" {
+        channel.receive_limit = parse_limit(val);
+    } else if key == "import limit" {
+        channel.import_limit = parse_limit(val);
+    } else if key == "export limit" {
+        channel.export_limit = parse_limit(val);
+    } else if key == "input filter" {
+        channel.input_filter = Some(val.trim().to_string());
+        channel.input_filter_kind = Some(classify_filter(val));
+    } else if key == "output filter" {
+        channel.output_filter = Some(val.trim().to_string());
+        channel.output_filter_kind = Some(classify_filter(val));
+    } else if key == "neighbor gr" || key == "gr state" {
+        channel.gr_state = Some(val.trim().to_lowercase());
+    }
+}
+
+/// Parse the capabilities advertised by the neighbor.
+/// The section ends with the first line not indented
+/// deeper than its header.
+fn parse_capabilities(
+    neighbor: &mut Neighbor,
+    indent: usize,
+    line: &str,
+) -> Result<State> {
+    if indentation(line) <= indent {
+        return parse_bgp_state(neighbor, line);
+    }
+    neighbor.capabilities.push(line.trim().to_string());
+    Ok(State::Capabilities(indent))
+}
+
+/// Count the leading whitespace of a line after its control code
+fn indentation(line: &str) -> usize {
+    let line = strip_control_code(line);
+    line.len() - line.trim_start().len()
+}
+
+/// Parse a table name. Trailing annotations like
+/// in `master4 (sorted)` are stripped.
+fn parse_table(s: &str) -> String {
+    let table = match s.find('(') {
+        Some(index) => &s[..index],
+        None => s,
+    };
+    table.trim().to_string()
+}
+
+/// Classify a channel filter as a built-in action,
+/// a named filter or an inline filter.
+fn classify_filter(filter: &str) -> String {
+    match filter.trim() {
+        "ACCEPT" => "accept",
+        "REJECT" => "reject",
+        "(unnamed)" => "inline",
+        _ => "named",
+    }
+    .to_string()
+}
+
+/// Parse a route limit. The value may be followed
+/// by an action, e.g. `90000 action restart`.
+fn parse_limit(s: &str) -> Option<u32> {
+    s.split_whitespace().next()?.parse().ok()
 }
 
 /// Change Stats
@@ -194,23 +404,31 @@ impl ChangeStats {
     }
 }
 
-/// Parse route change stats
+/// Parse route change stats. The routes of all
+/// channels are added up.
 fn parse_route_change_stats(
     neighbor: &mut Neighbor,
     line: &str,
 ) -> Result<State> {
+    if let Some(indent) = parse_description(neighbor, line) {
+        return Ok(State::Description(indent, Section::RouteChangeStats));
+    }
+    if line.trim().starts_with("Channel ") {
+        return parse_bgp_state(neighbor, line);
+    }
+
     if let Some(caps) = RE_KEY_VALUE.captures(line) {
         let key = caps["key"].to_lowercase();
         let val = caps["value"].to_string();
 
         if key == "import updates" {
             let stats = ChangeStats::parse(&val)?;
-            neighbor.routes_received = stats.received;
-            neighbor.routes_filtered = stats.filtered;
-            neighbor.routes_accepted = stats.accepted;
+            neighbor.routes_received += stats.received;
+            neighbor.routes_filtered += stats.filtered;
+            neighbor.routes_accepted += stats.accepted;
         } else if key == "export updates" {
             let stats = ChangeStats::parse(&val)?;
-            neighbor.routes_exported =
+            neighbor.routes_exported +=
                 stats.received - stats.rejected - stats.filtered;
         }
     }
@@ -221,6 +439,7 @@ fn parse_route_change_stats(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::RoutesSummary;
     use std::fs::File;
     use std::io::BufReader;
 
@@ -228,60 +447,294 @@ mod tests {
     fn test_parse_neighbor_header() {
         let line = "1002-R194_42    BGP        ---        up     09:39:25.123  Established";
         let mut neighbor = Neighbor::default();
-        parse_neighbor_header(&mut neighbor, &line).unwrap();
+        parse_neighbor_header(&mut neighbor, line).unwrap();
 
         assert_eq!(neighbor.id, "R194_42");
         assert_eq!(neighbor.state, "up");
         assert!(neighbor.uptime > 0.0);
     }
 
+    #[test]
+    fn test_parse_neighbor_header_relative() {
+        let line = "1002-R194_42    BGP        ---        up     1d12:34:56  Established";
+        let mut neighbor = Neighbor::default();
+        parse_neighbor_header(&mut neighbor, line).unwrap();
+
+        assert_eq!(neighbor.state, "up");
+        assert_eq!(neighbor.uptime, 131696.0);
+        let since = (Utc::now() - neighbor.since).num_seconds();
+        assert!((131696..131700).contains(&since));
+    }
+
     #[test]
     fn test_parse_neighbor_header_down() {
         let line = "1002-R_bhac01   BGP        ---        down   2023-04-19 09:08:10  Error: No listening socket";
         let mut neighbor = Neighbor::default();
-        parse_neighbor_header(&mut neighbor, &line).unwrap();
+        parse_neighbor_header(&mut neighbor, line).unwrap();
 
         assert_eq!(neighbor.id, "R_bhac01");
         assert_eq!(neighbor.state, "down");
         assert_eq!(neighbor.last_error, "Error: No listening socket");
+        assert_eq!(neighbor.last_error_class, Some("connection".into()));
+    }
+
+    #[test]
+    fn test_derive_asn() {
+        let re = Regex::new(r"as(\d+)$").unwrap();
+        assert_eq!(derive_asn("pb_0232_as62193", &re), Some(62193));
+        assert_eq!(derive_asn("R194_42", &re), None);
+
+        let re = Regex::new(r"^pb_(?P<id>\d+)_as(?P<asn>\d+)").unwrap();
+        assert_eq!(derive_asn("pb_0232_as62193", &re), Some(62193));
+    }
+
+    #[test]
+    fn test_classify_error() {
+        let tests = vec![
+            ("Error: No listening socket", Some("connection")),
+            ("Idle          BGP Error: Bad peer AS", Some("peer_as")),
+            ("Error: Hold timer expired", Some("hold_timer")),
+            ("Error: Connection refused", Some("connection")),
+            ("Error: Route limit exceeded", Some("route_limit")),
+            ("Received: Administrative shutdown", Some("admin_shutdown")),
+            ("Error: Invalid next hop", Some("other")),
+            ("Established", None),
+        ];
+        for (error, class) in tests {
+            assert_eq!(
+                classify_error(error),
+                class.map(|c| c.to_string()),
+                "{}",
+                error
+            );
+        }
     }
 
     #[test]
     fn test_parse_neighbor_header_idle() {
         let line = "1002-R192_158   BGP        ---        start  2023-04-20 12:01:52  Idle          BGP Error: Bad peer AS";
         let mut neighbor = Neighbor::default();
-        parse_neighbor_header(&mut neighbor, &line).unwrap();
+        parse_neighbor_header(&mut neighbor, line).unwrap();
+        assert_eq!(neighbor.state, "start");
+        assert_eq!(
+            neighbor.last_error,
+            "Idle          BGP Error: Bad peer AS"
+        );
+        assert_eq!(neighbor.last_error_class, Some("peer_as".into()));
+    }
+
+    #[test]
+    fn test_parse_neighbor_header_passive() {
+        let line = "1002-R_pp_61    BGP        ---        passive  2023-04-19 09:08:03  Passive";
+        let mut neighbor = Neighbor::default();
+        parse_neighbor_header(&mut neighbor, line).unwrap();
+        assert_eq!(neighbor.id, "R_pp_61");
+        assert_eq!(neighbor.state, "passive");
+        assert_eq!(neighbor.last_error, "Passive");
+        assert_eq!(neighbor.last_error_class, None);
+    }
+
+    #[test]
+    fn test_parse_neighbor_header_extra_column() {
+        let line = "1002-R194_42    BGP        master4    ---        up     2023-04-19 09:39:25  Established";
+        let mut neighbor = Neighbor::default();
+        parse_neighbor_header(&mut neighbor, line).unwrap();
+        assert_eq!(neighbor.id, "R194_42");
+        assert_eq!(neighbor.state, "up");
+
+        let line = "1002-R_bhac01   BGP        master4    down   2023-04-19 09:08:10  Error: No listening socket";
+        let mut neighbor = Neighbor::default();
+        parse_neighbor_header(&mut neighbor, line).unwrap();
+        assert_eq!(neighbor.id, "R_bhac01");
+        assert_eq!(neighbor.state, "down");
+        assert_eq!(neighbor.last_error, "Error: No listening socket");
+
+        let line = "1002-R_pp_60    BGP        upstream4  ---  start  2023-04-19 09:08:03  Passive";
+        let mut neighbor = Neighbor::default();
+        parse_neighbor_header(&mut neighbor, line).unwrap();
+        assert_eq!(neighbor.id, "R_pp_60");
+        assert_eq!(neighbor.state, "start");
+        assert_eq!(neighbor.last_error, "Passive");
     }
 
     #[test]
     fn test_parse_neighbor_meta() {
         let line = "1006-  Description:    AnniNET Software Development";
         let mut neighbor = Neighbor::default();
-        parse_neighbor_meta(&mut neighbor, &line).unwrap();
+        parse_neighbor_meta(&mut neighbor, line).unwrap();
         assert_eq!(neighbor.description, "AnniNET Software Development");
     }
 
+    #[test]
+    fn test_neighbor_parse_wrapped_description() {
+        let block: Block = vec![
+            "1002-R194_42    BGP        ---        up     2023-04-19 09:39:25  Established".into(),
+            "1006-  Description:    Packet Clearing House,".into(),
+            "                  Route Server Peering".into(),
+            "   BGP state:          Established".into(),
+            "    Neighbor address: 172.31.194.42".into(),
+            "    Neighbor AS:      42".into(),
+        ];
+        let neighbor = Neighbor::parse(block).unwrap();
+        assert_eq!(
+            neighbor.description,
+            "Packet Clearing House, Route Server Peering"
+        );
+        assert_eq!(neighbor.address, "172.31.194.42");
+        assert_eq!(neighbor.asn, 42);
+    }
+
+    #[test]
+    fn test_neighbor_reader_wrapped_description() {
+        let reply = "1002-R194_42    BGP        ---        up     2023-04-19 09:39:25  Established
+1006-  Description:    Packet Clearing House,
+      Route Server Peering
+   BGP state:          Established
+     Neighbor AS:      42
+   Channel ipv4
+     Table:          master4
+0000 
+";
+        let mut reader = NeighborReader::new(reply.as_bytes());
+        let neighbor = reader.next().unwrap();
+        assert_eq!(
+            neighbor.description,
+            "Packet Clearing House, Route Server Peering"
+        );
+        assert_eq!(neighbor.asn, 42);
+        assert_eq!(neighbor.channels[0].table, "master4");
+    }
+
+    #[test]
+    fn test_neighbor_parse_without_description() {
+        let block: Block = vec![
+            "1002-R194_42    BGP        ---        up     2023-04-19 09:39:25  Established".into(),
+            "1006-  BGP state:          Established".into(),
+            "    Neighbor address: 172.31.194.42".into(),
+        ];
+        let neighbor = Neighbor::parse(block).unwrap();
+        assert_eq!(neighbor.description, "");
+        assert_eq!(neighbor.address, "172.31.194.42");
+    }
+
     #[test]
     fn test_parse_neighbor_bgpstate() {
         let mut neighbor = Neighbor::default();
         let line = "   BGP state:          Established ";
-        let next = parse_bgp_state(&mut neighbor, &line).unwrap();
-        assert_eq!(next, State::BgpState);
+        let next = parse_bgp_state(&mut neighbor, line).unwrap();
+        assert_eq!(next, State::Bgp);
+        assert_eq!(neighbor.bgp_state, "established");
 
         let line = "   Neighbor address: 172.31.194.42";
-        parse_bgp_state(&mut neighbor, &line).unwrap();
+        parse_bgp_state(&mut neighbor, line).unwrap();
         let line = "     Neighbor AS:      42";
-        parse_bgp_state(&mut neighbor, &line).unwrap();
+        parse_bgp_state(&mut neighbor, line).unwrap();
 
         assert_eq!(neighbor.address, "172.31.194.42");
         assert_eq!(neighbor.asn, 42);
 
         let line =
             "     Route change stats:     received   rejected   filtered    ignored   accepted";
-        let next = parse_bgp_state(&mut neighbor, &line).unwrap();
+        let next = parse_bgp_state(&mut neighbor, line).unwrap();
         assert_eq!(next, State::RouteChangeStats);
     }
 
+    #[test]
+    fn test_parse_bgp_state_graceful_restart() {
+        let mut neighbor = Neighbor::default();
+        parse_bgp_state(&mut neighbor, "   Channel ipv4").unwrap();
+        parse_bgp_state(&mut neighbor, "     State:          UP").unwrap();
+        assert_eq!(neighbor.channels[0].state, "up");
+        assert_eq!(neighbor.channels[0].gr_state, None);
+
+        parse_bgp_state(&mut neighbor, "     GR wait").unwrap();
+        assert_eq!(neighbor.channels[0].gr_state, Some("wait".into()));
+
+        let line = "     Neighbor GR:    restart";
+        parse_bgp_state(&mut neighbor, line).unwrap();
+        assert_eq!(neighbor.channels[0].gr_state, Some("restart".into()));
+    }
+
+    #[test]
+    fn test_parse_capabilities() {
+        let mut neighbor = Neighbor::default();
+        let lines = [
+            "     Local capabilities",
+            "       Route refresh",
+            "     Neighbor capabilities",
+            "       Multiprotocol",
+            "         AF announced: ipv4",
+            "       Route refresh",
+            "     Session:          external route-server AS4",
+        ];
+        let mut state = State::Bgp;
+        for line in lines {
+            state = parse_line(&mut neighbor, state, line).unwrap();
+        }
+        assert_eq!(state, State::Bgp);
+        assert_eq!(
+            neighbor.capabilities,
+            vec!["Multiprotocol", "AF announced: ipv4", "Route refresh"]
+        );
+    }
+
+    #[test]
+    fn test_parse_session_type() {
+        let mut neighbor = Neighbor::default();
+        let line = "     Session:          external route-server AS4";
+        parse_bgp_state(&mut neighbor, line).unwrap();
+        assert_eq!(neighbor.session_type, Some("external".into()));
+
+        let line = "     BGP session:      internal";
+        parse_bgp_state(&mut neighbor, line).unwrap();
+        assert_eq!(neighbor.session_type, Some("internal".into()));
+    }
+
+    #[test]
+    fn test_parse_last_error() {
+        let block: Block = vec![
+            "1002-R192_158   BGP        ---        start  2023-04-20 12:01:52  Idle          BGP Error: Bad peer AS".into(),
+            "1006-  Description:    Example".into(),
+            "   BGP state:          Idle".into(),
+            "     Neighbor address: 172.31.192.158".into(),
+            "     Neighbor AS:      15169".into(),
+            "     Last error:       Hold timer expired".into(),
+            "     Error wait:       39.553/60".into(),
+        ];
+        let neighbor = Neighbor::parse(block).unwrap();
+        assert_eq!(neighbor.state, "start");
+        assert_eq!(neighbor.bgp_state, "idle");
+        assert_eq!(neighbor.last_error, "Hold timer expired");
+        assert_eq!(neighbor.last_error_class, Some("hold_timer".into()));
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let mut neighbor = Neighbor::default();
+        parse_bgp_state(&mut neighbor, "   Channel ipv4").unwrap();
+        let line = "     Table:          master4 (sorted)";
+        parse_bgp_state(&mut neighbor, line).unwrap();
+        assert_eq!(neighbor.channels[0].table, "master4");
+
+        assert_eq!(parse_table("t_R1.in"), "t_R1.in");
+        assert_eq!(parse_table("master6 "), "master6");
+    }
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!(parse_limit("40000"), Some(40000));
+        assert_eq!(parse_limit("90000 action restart"), Some(90000));
+        assert_eq!(parse_limit(""), None);
+    }
+
+    #[test]
+    fn test_classify_filter() {
+        assert_eq!(classify_filter("ACCEPT"), "accept");
+        assert_eq!(classify_filter("REJECT"), "reject");
+        assert_eq!(classify_filter("my_filter"), "named");
+        assert_eq!(classify_filter("(unnamed)"), "inline");
+    }
+
     #[test]
     fn test_neighbor_parse() {
         let block: Block = vec![
@@ -306,5 +759,136 @@ mod tests {
         let neighbor = &neighbors[0];
         assert_eq!(neighbor.id, "R194_42");
         assert_eq!(neighbor.address, "111.111.194.42");
+        assert_eq!(neighbor.session_type, Some("external".into()));
+        assert_eq!(neighbor.capabilities.len(), 11);
+        assert_eq!(neighbor.capabilities[3], "Extended message");
+        assert_eq!(neighbor.capabilities[10], "Enhanced refresh");
+        assert_eq!(neighbor.channels.len(), 1);
+        let channel = &neighbor.channels[0];
+        assert_eq!(channel.name, "ipv4");
+        assert_eq!(channel.state, "up");
+        assert_eq!(channel.receive_limit, Some(40000));
+        assert_eq!(channel.import_limit, Some(5000));
+        assert_eq!(channel.export_limit, Some(300000));
+        assert_eq!(channel.table, "master4");
+        assert_eq!(channel.input_filter, Some("(unnamed)".into()));
+        assert_eq!(channel.input_filter_kind, Some("inline".into()));
+
+        let neighbor = &neighbors[3];
+        assert_eq!(neighbor.id, "R_pp_60");
+        assert_eq!(neighbor.bgp_state, "passive");
+        assert_eq!(neighbor.channels[0].receive_limit, None);
+    }
+
+    #[test]
+    fn test_neighbor_reader_packed() {
+        let input =
+            File::open("tests/birdc/show-protocols-all-packed").unwrap();
+        let reader = NeighborReader::new(BufReader::new(input));
+        let neighbors: Vec<Neighbor> =
+            reader.filter(|n| !n.id.is_empty()).collect();
+        assert_eq!(neighbors.len(), 2);
+
+        let neighbor = &neighbors[0];
+        assert_eq!(neighbor.id, "R195_77");
+        assert_eq!(neighbor.description, "AS112");
+        assert_eq!(neighbor.asn, 112);
+        assert_eq!(neighbor.channels[0].table, "master4");
+        assert_eq!(neighbor.routes_received, 2);
+        assert_eq!(neighbor.routes_exported, 709981 - 6 - 174686);
+
+        let neighbor = &neighbors[1];
+        assert_eq!(neighbor.id, "R_bhac01");
+        assert_eq!(neighbor.state, "down");
+        assert_eq!(neighbor.description, "BHAC");
+        assert_eq!(neighbor.address, "111.111.192.161");
+        assert_eq!(neighbor.asn, 61437);
+        assert_eq!(neighbor.channels[0].table, "master6");
+        assert_eq!(neighbor.routes_received, 7);
+        assert_eq!(neighbor.routes_filtered, 3);
+        assert_eq!(neighbor.routes_accepted, 4);
+    }
+
+    #[test]
+    fn test_neighbor_reader_channels() {
+        let input =
+            File::open("tests/birdc/show-protocols-all-channels").unwrap();
+        let reader = NeighborReader::new(BufReader::new(input));
+        let neighbors: Vec<Neighbor> =
+            reader.filter(|n| !n.id.is_empty()).collect();
+        assert_eq!(neighbors.len(), 1);
+
+        let neighbor = &neighbors[0];
+        assert_eq!(neighbor.id, "R196_10");
+        let names: Vec<&str> =
+            neighbor.channels.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["ipv4", "ipv6"]);
+
+        let ipv4 = &neighbor.channels[0];
+        assert_eq!(ipv4.table, "master4");
+        assert_eq!(ipv4.receive_limit, Some(1000));
+        assert_eq!(ipv4.input_filter, Some("rs_import_v4".into()));
+        assert_eq!(ipv4.input_filter_kind, Some("named".into()));
+        assert_eq!(ipv4.output_filter_kind, Some("accept".into()));
+        assert_eq!(ipv4.gr_state, None);
+
+        let ipv6 = &neighbor.channels[1];
+        assert_eq!(ipv6.state, "up");
+        assert_eq!(ipv6.table, "master6");
+        assert_eq!(ipv6.receive_limit, Some(500));
+        assert_eq!(ipv6.input_filter_kind, Some("inline".into()));
+        assert_eq!(ipv6.output_filter_kind, Some("reject".into()));
+        assert_eq!(ipv6.gr_state, Some("wait".into()));
+
+        // The routes of both channels are added up
+        assert_eq!(neighbor.routes_received, 18);
+        assert_eq!(neighbor.routes_filtered, 3);
+        assert_eq!(neighbor.routes_accepted, 15);
+        assert_eq!(neighbor.routes_exported, 900);
+    }
+
+    #[test]
+    fn test_neighbor_reader_late_description() {
+        let input =
+            File::open("tests/birdc/show-protocols-all-late-description")
+                .unwrap();
+        let reader = NeighborReader::new(BufReader::new(input));
+        let neighbors: Vec<Neighbor> =
+            reader.filter(|n| !n.id.is_empty()).collect();
+        assert_eq!(neighbors.len(), 2);
+
+        let neighbor = &neighbors[0];
+        assert_eq!(neighbor.id, "R195_77");
+        assert_eq!(neighbor.description, "AS112 Anycast");
+        assert_eq!(neighbor.asn, 112);
+        assert_eq!(neighbor.routes_received, 2);
+
+        let neighbor = &neighbors[1];
+        assert_eq!(neighbor.id, "R_bhac01");
+        assert_eq!(neighbor.description, "BHAC");
+        assert_eq!(neighbor.channels[0].table, "master6");
+    }
+
+    #[test]
+    fn test_neighbor_routes_summary() {
+        let input = File::open("tests/birdc/show-protocols-all").unwrap();
+        let buf = BufReader::new(input);
+        let reader = NeighborReader::new(buf);
+        let neighbors: Vec<Neighbor> =
+            reader.filter(|n| !n.id.is_empty()).collect();
+
+        let summary = RoutesSummary::from(&neighbors[0]);
+        assert_eq!(
+            summary,
+            RoutesSummary {
+                received: 88,
+                filtered: 0,
+                accepted: 88,
+            }
+        );
+
+        let summary = RoutesSummary::from(&neighbors[2]);
+        assert_eq!(summary.received, 2);
+        assert_eq!(summary.accepted, 2);
     }
 }
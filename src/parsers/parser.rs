@@ -1,8 +1,17 @@
 use anyhow::Result;
+use lazy_static::lazy_static;
 use regex::Regex;
 use std::io::{BufRead, Lines};
 use std::iter::Peekable;
 
+lazy_static! {
+    /// Match the numeric control code at the start of a line.
+    /// Some builds pad the code with zeros or separate it
+    /// with a space instead of a dash.
+    static ref RE_CONTROL_CODE: Regex =
+        Regex::new(r"^(?P<code>\d{4,5})(?P<sep>[- ])").unwrap();
+}
+
 #[derive(thiserror::Error, Debug)]
 pub struct ParseError {
     line: String,
@@ -22,6 +31,49 @@ impl std::fmt::Display for ParseError {
     }
 }
 
+/// Get the control code of a line
+fn control_code(line: &str) -> Option<u32> {
+    let caps = RE_CONTROL_CODE.captures(line)?;
+    caps["code"].parse().ok()
+}
+
+/// Strip the control code of a line. Continuation lines have
+/// a space in place of the code, which is stripped instead,
+/// so the text of both kinds of lines is aligned.
+pub fn strip_control_code(line: &str) -> &str {
+    match RE_CONTROL_CODE.find(line) {
+        Some(code) => &line[code.end()..],
+        None => line.strip_prefix(' ').unwrap_or(line),
+    }
+}
+
+/// Strip the zero padding from the control code of a line,
+/// e.g. `01002-` becomes `1002-`.
+fn normalize_control_code(line: String) -> String {
+    let Some(caps) = RE_CONTROL_CODE.captures(&line) else {
+        return line;
+    };
+    let Ok(code) = caps["code"].parse::<u32>() else {
+        return line;
+    };
+    let rest = &line[caps.get(0).unwrap().end()..];
+    format!("{:04}{}{}", code, &caps["sep"], rest)
+}
+
+/// Check if a line starts a new block. The start pattern
+/// is matched with a dash separating the control code, so
+/// `1002 ` is matched by `1002-`.
+fn is_block_start(start: &Regex, line: &str) -> bool {
+    if start.is_match(line) {
+        return true;
+    }
+    let Some(code) = control_code(line) else {
+        return false;
+    };
+    let rest = RE_CONTROL_CODE.replace(line, "");
+    start.is_match(&format!("{:04}-{}", code, rest))
+}
+
 /// A block is a list of lines
 pub type Block = Vec<String>;
 
@@ -66,7 +118,7 @@ impl Iterator for BlockGroup {
 
             // Check next line in iterator
             if let Some(next) = self.iter.peek() {
-                if self.start.is_match(next) {
+                if is_block_start(&self.start, next) {
                     break;
                 }
             } else {
@@ -82,6 +134,25 @@ impl Iterator for BlockGroup {
     }
 }
 
+/// Read a complete reply as a single block. The reply ends
+/// with the first line whose control code is followed by a
+/// space. The greeting (`0001`) sent after connecting is
+/// part of the reply.
+pub fn read_reply<R: BufRead>(reader: R) -> Result<Block> {
+    let mut block = Block::new();
+    for line in reader.lines() {
+        let line = normalize_control_code(line?);
+        let last = RE_CONTROL_CODE.captures(&line).is_some_and(|caps| {
+            &caps["sep"] == " " && control_code(&line) != Some(1)
+        });
+        block.push(line);
+        if last {
+            break;
+        }
+    }
+    Ok(block)
+}
+
 /// A BlockIterator takes an object implementing the Read trait
 /// and a marker token
 /// which separates the input lines into blocks.
@@ -111,25 +182,26 @@ impl<R: BufRead> Iterator for BlockIterator<R> {
         // Create a peekable line iterator
         loop {
             // Read next line
-            let line = self.lines.next()?.ok()?;
+            let line = normalize_control_code(self.lines.next()?.ok()?);
 
             // Check stop marker
-            if line.starts_with("0000") {
-                return None;
-            }
-            if line.starts_with("9001") {
-                println!("ERROR: {}", line);
-                return None;
+            match control_code(&line) {
+                Some(0) => return None,
+                Some(9001) => {
+                    println!("ERROR: {}", line);
+                    return None;
+                }
+                _ => {}
             }
 
             block.push(line.clone());
 
             // Check next line in iterator
             if let Some(Ok(next)) = self.lines.peek() {
-                if self.start.is_match(next) {
+                if is_block_start(&self.start, next) {
                     break;
                 }
-                if next.starts_with("0000") {
+                if control_code(next) == Some(0) {
                     break;
                 }
             } else {
@@ -165,6 +237,61 @@ mod tests {
         assert_eq!(iter.next(), None);
     }
 
+    #[test]
+    fn test_block_iterator_control_codes() {
+        let re_start = Regex::new(r"1002-").unwrap();
+
+        // Space separated control codes
+        let input = "1002 line1\n line2\n1002 line3\n0000 \nline4\n";
+        let reader = BufReader::new(input.as_bytes());
+        let blocks: Vec<Block> =
+            BlockIterator::new(reader, &re_start).collect();
+        assert_eq!(
+            blocks,
+            vec![vec!["1002 line1", " line2"], vec!["1002 line3"]]
+        );
+
+        // Zero padded control codes
+        let input = "01002-line1\n line2\n01002-line3\n00000 \nline4\n";
+        let reader = BufReader::new(input.as_bytes());
+        let blocks: Vec<Block> =
+            BlockIterator::new(reader, &re_start).collect();
+        assert_eq!(
+            blocks,
+            vec![vec!["1002-line1", " line2"], vec!["1002-line3"]]
+        );
+    }
+
+    #[test]
+    fn test_strip_control_code() {
+        assert_eq!(
+            strip_control_code("1006-  Description: x"),
+            "  Description: x"
+        );
+        assert_eq!(strip_control_code("   BGP state: up"), "  BGP state: up");
+        assert_eq!(strip_control_code("0013 end"), "end");
+        assert_eq!(strip_control_code("text"), "text");
+    }
+
+    #[test]
+    fn test_read_reply() {
+        let input = "0001 BIRD 2.0.10 ready.\n1000-BIRD 2.0.10\n \
+                     line1\n0013-message\n line2\n0013 end\nnext\n";
+        let reader = BufReader::new(input.as_bytes());
+        let block = read_reply(reader).unwrap();
+        assert_eq!(
+            block,
+            vec![
+                "0001 BIRD 2.0.10 ready.",
+                "1000-BIRD 2.0.10",
+                " line1",
+                "0013-message",
+                " line2",
+                "0013 end",
+            ]
+        );
+    }
+
     #[test]
     fn test_block_group_iterator() {
         let block = vec![
@@ -0,0 +1,68 @@
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{
+    parsers::parser::{Block, Parse},
+    state::RouteCount,
+};
+
+lazy_static! {
+    /// Regex for the route count summary
+    static ref RE_ROUTE_COUNT: Regex = Regex::new(r"(?x)
+        (?P<routes>\d+)\s+of\s+
+        (?P<total>\d+)\s+routes\s+for\s+
+        (?P<networks>\d+)\s+networks
+        (\s+in\s+table\s+(?P<table>\S+))?
+    ").unwrap();
+}
+
+impl Parse for RouteCount {
+    /// Parse the output of show route count
+    fn parse(block: Block) -> Result<Self> {
+        for line in block.iter() {
+            if let Some(caps) = RE_ROUTE_COUNT.captures(line) {
+                return Ok(RouteCount {
+                    table: caps
+                        .name("table")
+                        .map(|t| t.as_str().to_string())
+                        .unwrap_or_default(),
+                    routes: caps["routes"].parse()?,
+                    routes_total: caps["total"].parse()?,
+                    networks: caps["networks"].parse()?,
+                });
+            }
+        }
+        Err(anyhow!("No route count in response"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        fs::File,
+        io::{BufRead, BufReader},
+    };
+
+    #[test]
+    fn test_parse() {
+        let file =
+            File::open("tests/birdc/show-route-table-master4-count").unwrap();
+        let reader = BufReader::new(file);
+        let block =
+            reader.lines().map(|l| l.unwrap()).collect::<Vec<String>>();
+        let count = RouteCount::parse(block).unwrap();
+        assert_eq!(count.table, "master4");
+        assert_eq!(count.routes, 712337);
+        assert_eq!(count.routes_total, 712455);
+        assert_eq!(count.networks, 712001);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let block = vec!["9001 syntax error".to_string()];
+        assert!(RouteCount::parse(block).is_err());
+    }
+}
@@ -1,13 +1,18 @@
+use std::net::IpAddr;
+
 use anyhow::{anyhow, Result};
 use lazy_static::lazy_static;
 use regex::Regex;
 
 use crate::{
+    config,
     parsers::{
         datetime,
         parser::{Block, BlockGroup, Parse},
     },
-    state::{Community, ExtCommunity, LargeCommunity, Route},
+    state::{
+        AsPathSegment, Community, ExtCommunity, LargeCommunity, NextHop, Route,
+    },
 };
 
 lazy_static! {
@@ -15,25 +20,41 @@ lazy_static! {
     static ref RE_ROUTE_HEADER: Regex = Regex::new(
         r"(?x)
           .*?
-          (?P<prefix>[0-9a-f:\./]+)?\s+   # Network
-          (?P<type>\w+)\s+ 
+          (?P<prefix>[0-9a-fA-F:\./]+)?\s+   # Network
+          (?P<type>[\w-]+)\s+           # Type
           \[
-            (?P<from_protocol>.*?)\s+(?P<age>[\d\-:\.\s]+)
+            (?P<from_protocol>.*?)
+            (\s+(?P<age>[\d\-:\.\s]+))?  # Age, may be missing
             (\s+from\s+(?P<learnt_from>.+))?
           \]\s+
           ((?P<primary>\*)\s+)?
-          \((?P<metric>\d+)\)\s+
+          \((?P<metric>[^)]*)\)
+          (\s+\[(AS(?P<origin_as>\d+))?(?P<origin>[ie?])\])?  # Origin
           .*$
     "
     )
     .unwrap();
 
+    /// Match the header of a special destination
+    /// without a protocol, e.g. `10.0.0.0/8 unreachable (200)`
+    static ref RE_DEST_HEADER: Regex = Regex::new(
+        r"(?x)
+          ^\S*?(?P<prefix>[0-9a-fA-F:\./]+)\s+
+          (?P<dest>unreachable|blackhole|prohibited)
+          (\s+(?P<primary>\*))?
+          (\s+\((?P<metric>[^)]*)\))?
+          \s*$
+    "
+    )
+    .unwrap();
+
     static ref RE_GATEWAY_INTERFACE: Regex = Regex::new(
         r"(?x)
           .*?via\s+
           (?P<gateway>[0-9a-f:\.]+)?\s+   # Gateway
           on\s+
-          (?P<interface>.+)
+          (?P<interface>\S+)
+          (\s+weight\s+(?P<weight>\d+))?  # Multipath weight
         ").unwrap();
 
     /// Regex for a Key: Value pair
@@ -45,10 +66,26 @@ lazy_static! {
     ").unwrap();
 
     /// BGP Community Regex
-    static ref RE_BGP_COMMUNITY: Regex = Regex::new(r"\((.+), (\d+), (\d+)\)").unwrap();
+    static ref RE_BGP_COMMUNITY: Regex =
+        Regex::new(r"\(([^,()]+), ([^,()]+), ([^,()]+)\)").unwrap();
 
     pub static ref RE_ROUTES_START: Regex = Regex::new(r"1007-\S").unwrap();
     static ref RE_ROUTE_START: Regex = Regex::new(r"1007-").unwrap();
+
+    /// Match a table header: `1007-Table master4:`
+    static ref RE_TABLE_HEADER: Regex =
+        Regex::new(r"^\d+-Table (?P<table>\S+):").unwrap();
+
+    /// Maximum number of communities per route
+    static ref MAX_COMMUNITIES_PER_ROUTE: Option<usize> =
+        config::get_max_communities_per_route();
+
+    /// Maximum number of routes per prefix
+    static ref MAX_ROUTES_PER_PREFIX: Option<usize> =
+        config::get_max_routes_per_prefix();
+
+    /// Remove private ASNs from AS paths
+    static ref REDACT_PRIVATE_ASN: bool = config::get_redact_private_asn();
 }
 
 #[derive(Debug, PartialEq)]
@@ -63,7 +100,7 @@ enum CommunityType {
 enum State {
     Start,
     Meta,
-    BGP,
+    Bgp,
     Communities(CommunityType),
 }
 
@@ -73,29 +110,133 @@ pub type PrefixGroup = Vec<Route>;
 
 impl Parse for PrefixGroup {
     fn parse(block: Block) -> Result<Self> {
-        let mut routes: PrefixGroup = Vec::new();
-        let mut iter = BlockGroup::new(block, &RE_ROUTE_START);
-        let mut prefix: String = String::new(); // Current prefix
+        parse_prefix_group(block, *MAX_ROUTES_PER_PREFIX)
+    }
+}
 
-        while let Some(block) = iter.next() {
-            if block[0].starts_with("0001") {
-                continue;
-            }
-            let mut route = Route::parse(block)?;
-            if route.network == "" {
-                route.network = prefix.clone();
-            } else {
-                prefix = route.network.clone();
-            }
+/// Parse the routes of a prefix group. If `max_routes` is set,
+/// additional routes are dropped.
+fn parse_prefix_group(
+    block: Block,
+    max_routes: Option<usize>,
+) -> Result<PrefixGroup> {
+    let mut routes: PrefixGroup = Vec::new();
+    let iter = BlockGroup::new(block, &RE_ROUTE_START);
+    let mut prefix: String = String::new(); // Current prefix
 
-            if route.neighbor_id.is_none() {
-                continue; // ??
-            }
-            routes.push(route);
+    for block in iter {
+        if max_routes.is_some_and(|max| routes.len() >= max) {
+            tracing::warn!(
+                network = prefix,
+                max = max_routes,
+                "too many routes for prefix, truncating"
+            );
+            break;
+        }
+        if block[0].starts_with("0001") {
+            continue;
+        }
+        let mut route = Route::parse(block)?;
+        if route.network.is_empty() {
+            route.network = prefix.clone();
+        } else {
+            prefix = route.network.clone();
+        }
+
+        // Skip routes without any information, e.g. when
+        // the block only consists of a table header.
+        if is_empty_route(&route) {
+            continue;
+        }
+        if let Some(max) = *MAX_COMMUNITIES_PER_ROUTE {
+            truncate_communities(&mut route, max);
+        }
+        if *REDACT_PRIVATE_ASN {
+            redact_private_asns(&mut route);
+        }
+        routes.push(route);
+    }
+    mark_group(&mut routes);
+
+    Ok(routes)
+}
+
+/// Set the group size on all routes of a prefix group and
+/// make sure at most the first route marked with `*` is
+/// the primary route.
+fn mark_group(routes: &mut [Route]) {
+    let size = routes.len();
+    let mut primary_found = false;
+    for route in routes.iter_mut() {
+        route.group_size = size;
+        if route.primary && primary_found {
+            route.primary = false;
+        }
+        primary_found |= route.primary;
+    }
+}
+
+/// Check if nothing was parsed for a route. The network
+/// is not considered, as it is carried over from the
+/// previous route of the prefix group.
+fn is_empty_route(route: &Route) -> bool {
+    route.neighbor_id.is_none()
+        && route.route_type.is_empty()
+        && route.gateway.is_empty()
+        && route.interface.is_empty()
+        && route.dest.is_none()
+        && route.bgp.as_path_segments.is_empty()
+}
+
+/// Get the table name if the line is a table header.
+/// All following routes belong to this table.
+pub fn parse_table_header(line: &str) -> Option<String> {
+    let caps = RE_TABLE_HEADER.captures(line)?;
+    Some(caps["table"].to_string())
+}
+
+/// Truncate the communities of a route to at most `max`
+/// entries per community type.
+fn truncate_communities(route: &mut Route, max: usize) {
+    let bgp = &mut route.bgp;
+    if bgp.communities.len() > max
+        || bgp.large_communities.len() > max
+        || bgp.ext_communities.len() > max
+    {
+        bgp.communities.truncate(max);
+        bgp.large_communities.truncate(max);
+        bgp.ext_communities.truncate(max);
+        route.communities_truncated = true;
+    }
+}
+
+/// Check if an ASN is reserved for private use (RFC 6996)
+fn is_private_asn(asn: u32) -> bool {
+    (64512..=65534).contains(&asn) || (4200000000..=4294967294).contains(&asn)
+}
+
+/// Remove private ASNs from the AS path of a route. The first (peer)
+/// and the last (origin) ASN are always kept.
+fn redact_private_asns(route: &mut Route) {
+    let redact = |len: usize| {
+        let mut pos = 0;
+        move |asn: &u32| {
+            let keep = pos == 0 || pos + 1 == len || !is_private_asn(*asn);
+            pos += 1;
+            keep
         }
+    };
 
-        Ok(routes)
+    let bgp = &mut route.bgp;
+    bgp.as_path.retain(redact(bgp.as_path.len()));
+
+    let len = bgp.as_path_segments.iter().map(|s| s.asns().len()).sum();
+    let mut keep = redact(len);
+    for segment in bgp.as_path_segments.iter_mut() {
+        segment.asns_mut().retain(&mut keep);
     }
+    bgp.as_path_segments
+        .retain(|segment| !segment.asns().is_empty());
 }
 
 /// Implement Parse for route
@@ -104,7 +245,7 @@ impl Parse for Route {
         let mut route = Route::default();
         let mut state = State::Start;
         for line in block.iter() {
-            match parse_line(&mut route, state, &line) {
+            match parse_line(&mut route, state, line) {
                 Ok(next_state) => state = next_state,
                 Err(e) => {
                     println!("Error parsing line: {}, {}", line, e);
@@ -112,6 +253,11 @@ impl Parse for Route {
                 }
             }
         }
+
+        route.next_hop_resolved = !route.gateway.is_empty()
+            && !route.bgp.next_hop.is_empty()
+            && route.gateway != route.bgp.next_hop;
+
         Ok(route)
     }
 }
@@ -121,7 +267,7 @@ fn parse_line(route: &mut Route, state: State, line: &str) -> Result<State> {
     match state {
         State::Start => parse_route_header(route, line),
         State::Meta => parse_route_meta(route, line),
-        State::BGP => parse_route_bgp(route, line),
+        State::Bgp => parse_route_bgp(route, line),
         State::Communities(community_type) => {
             parse_route_communities(route, community_type, line)
         }
@@ -133,16 +279,27 @@ fn parse_route_header(route: &mut Route, line: &str) -> Result<State> {
     let caps = RE_ROUTE_HEADER.captures(line);
     if let Some(caps) = caps {
         if let Some(prefix) = caps.name("prefix") {
-            route.network = prefix.as_str().to_string();
+            route.network = normalize_network(prefix.as_str());
         }
+        // An invalid age does not fail the route
         if let Some(age) = caps.name("age") {
-            route.age = datetime::parse_duration_sec(age.as_str())?;
+            match datetime::parse(age.as_str()) {
+                Ok(timestamp) => {
+                    route.age = datetime::duration_sec(&timestamp);
+                    route.age_timestamp = Some(timestamp);
+                }
+                Err(err) => {
+                    tracing::warn!(age = age.as_str(), "invalid age: {}", err);
+                }
+            }
         }
-        if let Some(_) = caps.name("primary") {
+        route.active = true;
+        if caps.name("primary").is_some() {
             route.primary = true;
         }
         if let Some(metric) = caps.name("metric") {
-            route.metric = metric.as_str().parse::<u32>()?;
+            route.preference = parse_metric(metric.as_str());
+            route.metric = route.preference;
         }
         if let Some(from) = caps.name("learnt_from") {
             route.learnt_from = Some(from.as_str().to_string());
@@ -150,29 +307,145 @@ fn parse_route_header(route: &mut Route, line: &str) -> Result<State> {
         if let Some(proto) = caps.name("from_protocol") {
             route.neighbor_id = Some(proto.as_str().to_string());
         }
+        if is_dest(&caps["type"]) {
+            route.dest = Some(caps["type"].to_string());
+        }
+        // The origin is overwritten by the BGP attributes if present
+        if let Some(origin) = caps.name("origin") {
+            route.bgp.origin = parse_origin_code(origin.as_str());
+        }
+        if let Some(asn) = caps.name("origin_as") {
+            route.bgp.origin_as = asn.as_str().parse().ok();
+        }
+
+        return Ok(State::Meta);
+    }
 
+    // Special destinations may lack the protocol
+    if let Some(caps) = RE_DEST_HEADER.captures(line) {
+        route.network = normalize_network(&caps["prefix"]);
+        route.dest = Some(caps["dest"].to_string());
+        route.active = true;
+        route.primary = caps.name("primary").is_some();
+        if let Some(metric) = caps.name("metric") {
+            route.preference = parse_metric(metric.as_str());
+            route.metric = route.preference;
+        }
         return Ok(State::Meta);
     }
 
     Ok(State::Start)
 }
 
+/// Get the origin from the code in the route header,
+/// using the same names as the `BGP.origin` attribute.
+fn parse_origin_code(code: &str) -> String {
+    match code {
+        "i" => "IGP",
+        "e" => "EGP",
+        _ => "Incomplete",
+    }
+    .to_string()
+}
+
+/// Normalize a network to its canonical form, e.g.
+/// `2001:DB8:0::/32` becomes `2001:db8::/32`. Networks
+/// which can not be parsed are returned as they are.
+fn normalize_network(network: &str) -> String {
+    let (addr, len) = match network.split_once('/') {
+        Some((addr, len)) => (addr, Some(len)),
+        None => (network, None),
+    };
+    let Ok(addr) = addr.parse::<IpAddr>() else {
+        return network.to_string();
+    };
+    match len {
+        Some(len) => format!("{}/{}", addr, len),
+        None => addr.to_string(),
+    }
+}
+
+/// Parse the route metric, which is the preference of the
+/// route. An invalid metric does not fail the route and
+/// defaults to 0.
+fn parse_metric(s: &str) -> u32 {
+    // The metric may be followed by an IGP metric: (100/20)
+    let metric = s.split('/').next().unwrap_or("");
+    metric.trim().parse().unwrap_or_else(|_| {
+        tracing::warn!(metric = s, "invalid route metric");
+        0
+    })
+}
+
 /// Parse route type (list of strings)
 fn parse_route_type(s: &str) -> Result<Vec<String>> {
     let route_types = s.split(" ").map(|s| s.to_string()).collect();
     Ok(route_types)
 }
 
+/// Split the protocol of the route type into the kind
+/// and the OSPF subtype: `OSPF-E2` becomes `("OSPF", Some("E2"))`.
+fn parse_protocol_kind(s: &str) -> (String, Option<String>) {
+    match s.split_once('-') {
+        Some((kind, subtype)) if kind == "OSPF" => {
+            (kind.to_string(), Some(subtype.to_string()))
+        }
+        _ => (s.to_string(), None),
+    }
+}
+
+/// Check if the route type is a special destination
+fn is_dest(s: &str) -> bool {
+    matches!(s, "unreachable" | "blackhole" | "prohibited")
+}
+
+/// Add a next hop to the route. The first next hop
+/// is the gateway of the route.
+fn push_next_hop(route: &mut Route, next_hop: NextHop) {
+    if route.next_hops.is_empty() {
+        route.gateway.clone_from(&next_hop.gateway);
+        route.interface.clone_from(&next_hop.interface);
+    }
+    route.next_hops.push(next_hop);
+}
+
 /// Parse route meta
 fn parse_route_meta(route: &mut Route, line: &str) -> Result<State> {
+    // Special destinations have no gateway
+    let meta = line.trim().trim_start_matches(|c: char| {
+        c.is_ascii_digit() || c == '-' || c.is_whitespace()
+    });
+    if is_dest(meta) {
+        route.dest = Some(meta.to_string());
+        return Ok(State::Meta);
+    }
+
+    // Directly connected: `dev eth0` or `via dev eth0`
+    let direct = meta.strip_prefix("via ").unwrap_or(meta);
+    if let Some(interface) = direct.strip_prefix("dev ") {
+        let next_hop = NextHop {
+            interface: interface.trim().to_string(),
+            ..Default::default()
+        };
+        push_next_hop(route, next_hop);
+        return Ok(State::Meta);
+    }
+    if meta == "via recursive" {
+        route.dest = Some("recursive".to_string());
+        return Ok(State::Meta);
+    }
+
     let caps = RE_GATEWAY_INTERFACE.captures(line);
     if let Some(caps) = caps {
-        if let Some(gateway) = caps.name("gateway") {
-            route.gateway = gateway.as_str().to_string();
-        }
-        if let Some(interface) = caps.name("interface") {
-            route.interface = interface.as_str().to_string();
-        }
+        let next_hop = NextHop {
+            gateway: caps
+                .name("gateway")
+                .map(|g| g.as_str().to_string())
+                .unwrap_or_default(),
+            interface: caps["interface"].to_string(),
+            weight: caps.name("weight").and_then(|w| w.as_str().parse().ok()),
+        };
+        push_next_hop(route, next_hop);
         return Ok(State::Meta);
     }
 
@@ -182,33 +455,126 @@ fn parse_route_meta(route: &mut Route, line: &str) -> Result<State> {
             if let Some(value) = caps.name("value") {
                 if key.as_str() == "Type" {
                     route.route_type = parse_route_type(value.as_str())?;
+                    if let Some(kind) = route.route_type.first() {
+                        let (kind, ospf_type) = parse_protocol_kind(kind);
+                        route.protocol_kind = Some(kind);
+                        route.ospf_type = ospf_type;
+                    }
                 }
             }
         }
     }
 
-    Ok(State::BGP)
+    Ok(State::Bgp)
+}
+
+/// Parse the label of a BGP-LU route. The label is printed
+/// as a stack like `100/200`, the first label is used.
+fn parse_bgp_label(s: &str) -> Option<u32> {
+    s.split(|c: char| c == '/' || c.is_whitespace())
+        .find(|label| !label.is_empty())?
+        .parse()
+        .ok()
 }
 
 /// Parse AS path
-fn parse_as_path(s: &str) -> Result<Vec<u32>> {
-    let mut as_path: Vec<u32> = vec![];
-    for asn in s.split(" ") {
-        as_path.push(asn.parse()?);
+fn parse_as_path(s: &str) -> Result<Vec<AsPathSegment>> {
+    let s = normalize_as_path(s)
+        .replace('{', " { ")
+        .replace('}', " } ")
+        .replace('(', " ( ")
+        .replace(')', " ) ");
+
+    let mut segments: Vec<AsPathSegment> = vec![];
+    let (mut set, mut confed) = (false, false);
+    // Every delimiter starts a new segment
+    let mut next_segment = true;
+    for token in s.split_whitespace() {
+        match token {
+            "{" => (set, next_segment) = (true, true),
+            "}" => (set, next_segment) = (false, true),
+            "(" => (confed, next_segment) = (true, true),
+            ")" => (confed, next_segment) = (false, true),
+            asn => {
+                let asn = parse_asn(asn)?;
+                if next_segment {
+                    segments.push(AsPathSegment::new(set, confed));
+                    next_segment = false;
+                }
+                if let Some(segment) = segments.last_mut() {
+                    segment.asns_mut().push(asn);
+                }
+            }
+        }
+    }
+    Ok(segments)
+}
+
+/// BIRD 3 may label the segments of the AS path, e.g.
+/// `AS_SEQUENCE 65001 AS_SET 65002 65003`. Rewrite these
+/// to the BIRD 2 notation `65001 {65002 65003}`.
+fn normalize_as_path(s: &str) -> String {
+    if !s.contains("AS_") {
+        return s.to_string();
+    }
+    let mut tokens: Vec<&str> = vec![];
+    let mut close = "";
+    for token in s.split_whitespace() {
+        let label = token.trim_end_matches(':');
+        let (open, next_close) = match label {
+            "AS_SEQUENCE" => ("", ""),
+            "AS_SET" => ("{", "}"),
+            "AS_CONFED_SEQUENCE" => ("(", ")"),
+            "AS_CONFED_SET" => ("({", "})"),
+            _ => {
+                tokens.push(token);
+                continue;
+            }
+        };
+        tokens.push(close);
+        tokens.push(open);
+        close = next_close;
+    }
+    tokens.push(close);
+    tokens.join(" ")
+}
+
+/// Parse an ASN in asplain or asdot (`X.Y`) notation.
+/// Asdot is converted to asplain as `X * 65536 + Y`.
+fn parse_asn(s: &str) -> Result<u32> {
+    match s.split_once('.') {
+        Some((high, low)) => {
+            let high: u16 = high.parse()?;
+            let low: u16 = low.parse()?;
+            Ok(((high as u32) << 16) | low as u32)
+        }
+        None => Ok(s.parse()?),
     }
-    Ok(as_path)
 }
 
 /// Parse BGP community
 fn parse_community(s: &str) -> Result<Community> {
-    let s = s[1..s.len() - 1].to_string(); // Strip braces
-    let tokens: Vec<&str> = s.split(",").collect();
+    // Strip braces if present
+    let s = s
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .unwrap_or(s);
+    let tokens: Vec<&str> = s.split([',', ':']).collect();
     if tokens.len() != 2 {
         return Err(anyhow!("Invalid community: {}", s));
     }
     Ok(Community(tokens[0].parse()?, tokens[1].parse()?))
 }
 
+/// Parse a community value, which may be hex encoded
+fn parse_community_value(s: &str) -> u32 {
+    if let Some(hex) = s.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16).unwrap_or(0)
+    } else {
+        s.parse().unwrap_or(0)
+    }
+}
+
 /// Parse a list of ext communities
 fn parse_ext_communities(s: &str) -> Result<Vec<ExtCommunity>> {
     let communities: Vec<ExtCommunity> = RE_BGP_COMMUNITY
@@ -216,14 +582,27 @@ fn parse_ext_communities(s: &str) -> Result<Vec<ExtCommunity>> {
         .map(|c| {
             ExtCommunity(
                 c[1].to_string(),
-                c[2].parse().unwrap_or(0),
-                c[3].parse().unwrap_or(0),
+                parse_community_value(&c[2]),
+                parse_community_value(&c[3]),
             )
         })
         .collect();
     Ok(communities)
 }
 
+/// Get the community type from the attribute name
+fn parse_community_type(line: &str) -> Option<CommunityType> {
+    if line.starts_with("BGP.community") {
+        Some(CommunityType::Standard)
+    } else if line.starts_with("BGP.large_community") {
+        Some(CommunityType::Large)
+    } else if line.starts_with("BGP.ext_community") {
+        Some(CommunityType::Extended)
+    } else {
+        None
+    }
+}
+
 /// Parse a list of large communities
 fn parse_large_communities(s: &str) -> Result<Vec<LargeCommunity>> {
     let communities: Vec<LargeCommunity> = RE_BGP_COMMUNITY
@@ -254,20 +633,23 @@ fn parse_route_communities(
     community_type: CommunityType,
     line: &str,
 ) -> Result<State> {
-    let mut line = line.trim_start();
-    let next_type = if line.starts_with("BGP.community") {
-        CommunityType::Standard
-    } else if line.starts_with("BGP.large_community") {
-        CommunityType::Large
-    } else if line.starts_with("BGP.ext_community") {
-        CommunityType::Extended
-    } else {
-        community_type
+    let attr = line.trim_start();
+    let next_type = match parse_community_type(attr) {
+        Some(next_type) => next_type,
+        // Other attributes may follow the communities
+        None if attr.starts_with("BGP.") => {
+            return parse_route_bgp(route, line)
+        }
+        None => community_type,
     };
+    let mut line = attr;
 
-    // Strip everything before the colon
-    if let Some(index) = line.find(':') {
-        line = &line[index + 1..];
+    // Strip the attribute name. Continuation lines
+    // have no attribute name.
+    if line.starts_with("BGP.") {
+        if let Some(index) = line.find(':') {
+            line = &line[index + 1..];
+        }
     }
     line = line.trim_start();
     if line.is_empty() {
@@ -299,8 +681,21 @@ fn parse_route_communities(
     Ok(State::Communities(next_type))
 }
 
+/// Parse a numeric value. Some versions of bird print
+/// a `-` if the value is not set.
+fn parse_optional_value(s: &str) -> Result<Option<u32>> {
+    match s.trim() {
+        "-" | "" => Ok(None),
+        value => Ok(Some(value.parse()?)),
+    }
+}
+
 /// Parse route BGP
 fn parse_route_bgp(route: &mut Route, line: &str) -> Result<State> {
+    if let Some(community_type) = parse_community_type(line.trim_start()) {
+        return parse_route_communities(route, community_type, line);
+    }
+
     // Parse key value info
     if let Some(caps) = RE_KEY_VALUE.captures(line) {
         let key = caps["key"].to_lowercase();
@@ -309,24 +704,35 @@ fn parse_route_bgp(route: &mut Route, line: &str) -> Result<State> {
         if key == "bgp.origin" {
             route.bgp.origin = val;
         } else if key == "bgp.as_path" {
-            route.bgp.as_path = parse_as_path(&val)?;
+            let segments = parse_as_path(&val)?;
+            route.bgp.as_path =
+                segments.iter().flat_map(|s| s.asns()).copied().collect();
+            route.bgp.as_path_segments = segments;
         } else if key == "bgp.next_hop" {
             route.bgp.next_hop = val;
         } else if key == "bgp.med" {
-            route.bgp.med = val.parse()?;
+            route.bgp.med = parse_optional_value(&val)?;
+        } else if key == "bgp.encap" || key == "bgp.tunnel_encap" {
+            route.bgp.encap = Some(val.trim().to_string());
+        } else if key == "bgp.vni" {
+            route.bgp.vni = val.trim().parse().ok();
+        } else if key == "bgp.mpls_label_stack" || key == "bgp.mpls_label" {
+            route.bgp.bgp_label = parse_bgp_label(&val);
         } else if key == "bgp.local_pref" {
-            route.bgp.local_pref = val.parse()?;
+            route.bgp.local_pref = parse_optional_value(&val)?;
             // After this the Communities start
             return Ok(State::Communities(CommunityType::Standard));
         }
     }
 
-    Ok(State::BGP)
+    Ok(State::Bgp)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parsers::parser::BlockIterator;
+    use std::{fs::File, io::BufReader};
 
     #[test]
     fn test_match_route_header() {
@@ -340,6 +746,100 @@ mod tests {
         println!("{:?}", caps);
     }
 
+    #[test]
+    fn test_match_route_header_type() {
+        let line =
+            "1007-203.17.254.0/24      unicast [R192_172 2023-04-19 09:28:42] * (100) [AS7545i]";
+        let caps = RE_ROUTE_HEADER.captures(line).unwrap();
+        assert_eq!(&caps["type"], "unicast");
+
+        let line =
+            "1007-10.0.0.0/8           L3VPN [R192_172 2023-04-19 09:28:42] * (100) [AS7545i]";
+        let caps = RE_ROUTE_HEADER.captures(line).unwrap();
+        assert_eq!(&caps["type"], "L3VPN");
+        assert_eq!(&caps["from_protocol"], "R192_172");
+
+        let line =
+            "1007-10.0.0.0/8           static-unicast [static1 2023-04-19 09:08:03] * (200) [i]";
+        let caps = RE_ROUTE_HEADER.captures(line).unwrap();
+        assert_eq!(&caps["type"], "static-unicast");
+        assert_eq!(&caps["from_protocol"], "static1");
+    }
+
+    #[test]
+    fn test_parse_table_header() {
+        let table = parse_table_header("1007-Table master4:");
+        assert_eq!(table, Some("master4".into()));
+        let table = parse_table_header("1007-Table t_R1.in:");
+        assert_eq!(table, Some("t_R1.in".into()));
+        let line = "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:35:57] * (100)";
+        assert_eq!(parse_table_header(line), None);
+    }
+
+    #[test]
+    fn test_parse_route_header_without_age() {
+        let line = "1007-10.0.0.0/8           unicast [kernel1] * (10)";
+        let mut route = Route::default();
+        let next = parse_route_header(&mut route, line).unwrap();
+        assert_eq!(next, State::Meta);
+        assert_eq!(route.network, "10.0.0.0/8");
+        assert_eq!(route.neighbor_id, Some("kernel1".into()));
+        assert_eq!(route.age, 0.0);
+        assert_eq!(route.metric, 10);
+        assert!(route.primary);
+
+        let line = "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:28:42 from 10.0.0.1] * (100) [AS1i]";
+        let mut route = Route::default();
+        parse_route_header(&mut route, line).unwrap();
+        assert_eq!(route.neighbor_id, Some("R1".into()));
+        assert_eq!(route.learnt_from, Some("10.0.0.1".into()));
+        assert!(route.age > 0.0);
+    }
+
+    #[test]
+    fn test_parse_route_header_age_timestamp() {
+        let line =
+            "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:28:42] * (100) [AS1i]";
+        let mut route = Route::default();
+        parse_route_header(&mut route, line).unwrap();
+        let timestamp = route.age_timestamp.unwrap();
+        assert_eq!(timestamp.date_naive().to_string(), "2023-04-19");
+
+        let line = "1007-10.0.0.0/8  unicast [R1 10:38:20.602] * (100) [AS1i]";
+        let mut route = Route::default();
+        parse_route_header(&mut route, line).unwrap();
+        assert!(route.age_timestamp.is_some());
+
+        // An invalid age does not fail the route
+        let line = "1007-10.0.0.0/8  unicast [R1 99:99:99] * (100) [AS1i]";
+        let mut route = Route::default();
+        parse_route_header(&mut route, line).unwrap();
+        assert_eq!(route.neighbor_id, Some("R1".into()));
+        assert_eq!(route.age_timestamp, None);
+        assert_eq!(route.age, 0.0);
+    }
+
+    #[test]
+    fn test_parse_route_header_origin() {
+        let tests = vec![
+            ("[AS7545i]", "IGP", Some(7545)),
+            ("[AS7545e]", "EGP", Some(7545)),
+            ("[AS7545?]", "Incomplete", Some(7545)),
+            ("[i]", "IGP", None),
+            ("", "", None),
+        ];
+        for (suffix, origin, origin_as) in tests {
+            let line = format!(
+                "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:35:57] * (100) {}",
+                suffix
+            );
+            let mut route = Route::default();
+            parse_route_header(&mut route, &line).unwrap();
+            assert_eq!(route.bgp.origin, origin, "{}", suffix);
+            assert_eq!(route.bgp.origin_as, origin_as, "{}", suffix);
+        }
+    }
+
     #[test]
     fn test_match_route_header_follow() {
         let line =
@@ -348,6 +848,161 @@ mod tests {
         println!("{:?}", caps.name("from_protocol").unwrap().as_str());
     }
 
+    #[test]
+    fn test_parse_prefix_group_alternate_path() {
+        let block: Block = vec![
+            "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:35:57] * (100) [AS1i]"
+                .into(),
+            " \tvia 10.0.0.1 on eth0".into(),
+            "1008-\tType: BGP univ".into(),
+            "1012-\tBGP.origin: IGP".into(),
+            " \tBGP.as_path: 1".into(),
+            "1007-           unicast [R2 2023-04-19 09:35:57 from 10.0.0.20] (100) [AS2i]"
+                .into(),
+            " \tvia 10.0.0.2 on eth0".into(),
+            "1008-\tType: BGP univ".into(),
+            "1012-\tBGP.origin: IGP".into(),
+            " \tBGP.as_path: 2".into(),
+        ];
+        let routes = PrefixGroup::parse(block).unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[1].network, "10.0.0.0/8");
+        assert_eq!(routes[1].neighbor_id, Some("R2".into()));
+        assert_eq!(routes[1].learnt_from, Some("10.0.0.20".into()));
+        assert_eq!(routes[1].gateway, "10.0.0.2");
+        assert_eq!(routes[1].bgp.as_path, vec![2]);
+
+        // A table header without routes is skipped
+        let block: Block = vec!["1007-Table master4:".into()];
+        let routes = PrefixGroup::parse(block).unwrap();
+        assert!(routes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_prefix_group_primary() {
+        let block: Block = vec![
+            "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:35:57] (100) [AS1i]"
+                .into(),
+            " \tvia 10.0.0.1 on eth0".into(),
+            "1007-           unicast [R2 2023-04-19 09:35:57] * (100) [AS2i]"
+                .into(),
+            " \tvia 10.0.0.2 on eth0".into(),
+            "1007-           unicast [R3 2023-04-19 09:35:57] (100) [AS3i]"
+                .into(),
+            " \tvia 10.0.0.3 on eth0".into(),
+        ];
+        let routes = PrefixGroup::parse(block).unwrap();
+        assert_eq!(routes.len(), 3);
+        assert!(routes.iter().all(|r| r.group_size == 3));
+        let primary: Vec<&str> = routes
+            .iter()
+            .filter(|r| r.primary)
+            .filter_map(|r| r.neighbor_id.as_deref())
+            .collect();
+        assert_eq!(primary, vec!["R2"]);
+
+        let mut routes = routes;
+        routes[2].primary = true;
+        mark_group(&mut routes);
+        assert!(routes[1].primary);
+        assert!(!routes[2].primary);
+
+        // Without a star there is no primary route
+        for route in routes.iter_mut() {
+            route.primary = false;
+        }
+        mark_group(&mut routes);
+        assert!(routes.iter().all(|r| !r.primary));
+    }
+
+    #[test]
+    fn test_parse_route_active() {
+        let line =
+            "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:35:57] * (100) [AS1i]";
+        let mut route = Route::default();
+        parse_route_header(&mut route, line).unwrap();
+        assert!(route.primary);
+        assert!(route.active);
+
+        let line =
+            "1007-10.0.0.0/8  unicast [R2 2023-04-19 09:35:57] (100) [AS2i]";
+        let mut route = Route::default();
+        parse_route_header(&mut route, line).unwrap();
+        assert!(!route.primary);
+        assert!(route.active);
+    }
+
+    #[test]
+    fn test_parse_prefix_group_max_routes() {
+        let mut block: Block = vec![];
+        for i in 1..=5 {
+            block.push(format!(
+                "1007-10.0.0.0/8  unicast [R{} 2023-04-19 09:35:57] (100)",
+                i
+            ));
+            block.push(format!(" \tvia 10.0.0.{} on eth0", i));
+        }
+        let routes = parse_prefix_group(block.clone(), Some(3)).unwrap();
+        assert_eq!(routes.len(), 3);
+        assert_eq!(routes[2].neighbor_id, Some("R3".into()));
+        assert!(routes.iter().all(|r| r.group_size == 3));
+
+        let routes = parse_prefix_group(block, None).unwrap();
+        assert_eq!(routes.len(), 5);
+    }
+
+    #[test]
+    fn test_normalize_network() {
+        let tests = vec![
+            ("2001:db8::/32", "2001:db8::/32"),
+            ("2001:DB8::/32", "2001:db8::/32"),
+            ("2001:0db8:0000::/32", "2001:db8::/32"),
+            ("2001:db8:0:0:0:0:0:1/128", "2001:db8::1/128"),
+            ("10.0.0.0/8", "10.0.0.0/8"),
+            ("invalid", "invalid"),
+        ];
+        for (network, expected) in tests {
+            assert_eq!(normalize_network(network), expected);
+        }
+
+        let line =
+            "1007-2001:DB8:0::/32  unicast [R1 2023-04-19 09:35:57] * (100)";
+        let mut route = Route::default();
+        parse_route_header(&mut route, line).unwrap();
+        assert_eq!(route.network, "2001:db8::/32");
+    }
+
+    #[test]
+    fn test_parse_metric() {
+        assert_eq!(parse_metric("100"), 100);
+        assert_eq!(parse_metric("150/20"), 150);
+        assert_eq!(parse_metric("abc"), 0);
+        assert_eq!(parse_metric("99999999999"), 0);
+    }
+
+    #[test]
+    fn test_parse_route_preference() {
+        let line = "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:35:57] * (100/20) [AS1i]";
+        let mut route = Route::default();
+        parse_route_header(&mut route, line).unwrap();
+        assert_eq!(route.preference, 100);
+        assert_eq!(route.metric, 100);
+    }
+
+    #[test]
+    fn test_parse_route_invalid_metric() {
+        let block: Block = vec![
+            "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:35:57] * (abc) [AS1i]"
+                .into(),
+            " \tvia 172.31.193.103 on vx0".into(),
+            "1008-\tType: BGP univ".into(),
+        ];
+        let routes = PrefixGroup::parse(block).unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].network, "10.0.0.0/8");
+        assert_eq!(routes[0].metric, 0);
+    }
+
     #[test]
     fn test_parse_route_meta() {
         let mut route = Route::default();
@@ -356,17 +1011,308 @@ mod tests {
         assert_eq!(state, State::Meta);
         let line = "1008-   Type: BGP univ";
         let state = parse_route_meta(&mut route, line).unwrap();
-        assert_eq!(state, State::BGP);
+        assert_eq!(state, State::Bgp);
 
         assert_eq!(route.gateway, "172.31.195.39");
         assert_eq!(route.interface, "vx0");
     }
 
+    #[test]
+    fn test_parse_asn() {
+        assert_eq!(parse_asn("1.10").unwrap(), 65546);
+        assert_eq!(parse_asn("0.553").unwrap(), 553);
+        assert_eq!(parse_asn("4200000001").unwrap(), 4200000001);
+        assert!(parse_asn("1.65536").is_err());
+        assert!(parse_asn("1.").is_err());
+
+        let as_path = parse_as_path("553 1.10 60824").unwrap();
+        assert_eq!(
+            as_path,
+            vec![AsPathSegment::Sequence(vec![553, 65546, 60824])]
+        );
+    }
+
+    #[test]
+    fn test_parse_as_path_segments() {
+        let as_path =
+            parse_as_path("65000 {65001 65002} 65003 (65010 65011) ({65020})")
+                .unwrap();
+        assert_eq!(
+            as_path,
+            vec![
+                AsPathSegment::Sequence(vec![65000]),
+                AsPathSegment::Set(vec![65001, 65002]),
+                AsPathSegment::Sequence(vec![65003]),
+                AsPathSegment::ConfedSequence(vec![65010, 65011]),
+                AsPathSegment::ConfedSet(vec![65020]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_as_path_kinds() {
+        let mut route = Route::default();
+        parse_route_bgp(&mut route, " \tBGP.as_path: 65000 65001").unwrap();
+        assert_eq!(route.bgp.as_path, vec![65000, 65001]);
+        assert_eq!(
+            route.bgp.as_path_segments,
+            vec![AsPathSegment::Sequence(vec![65000, 65001])]
+        );
+
+        let mut route = Route::default();
+        let line = " \tBGP.as_path: 65000 {65001 65002} 65003";
+        parse_route_bgp(&mut route, line).unwrap();
+        assert_eq!(route.bgp.as_path, vec![65000, 65001, 65002, 65003]);
+        assert_eq!(
+            route.bgp.as_path_segments,
+            vec![
+                AsPathSegment::Sequence(vec![65000]),
+                AsPathSegment::Set(vec![65001, 65002]),
+                AsPathSegment::Sequence(vec![65003]),
+            ]
+        );
+
+        let mut route = Route::default();
+        parse_route_bgp(&mut route, " \tBGP.as_path: (65010) 65000").unwrap();
+        assert_eq!(route.bgp.as_path, vec![65010, 65000]);
+        assert_eq!(
+            route.bgp.as_path_segments,
+            vec![
+                AsPathSegment::ConfedSequence(vec![65010]),
+                AsPathSegment::Sequence(vec![65000]),
+            ]
+        );
+        assert_eq!(route.bgp.as_path_len(), 1);
+    }
+
+    #[test]
+    fn test_normalize_as_path() {
+        let tests = vec![
+            ("65001 65002", "65001 65002"),
+            (
+                "AS_SEQUENCE 65001 AS_SET 65002 65003",
+                "65001 { 65002 65003 }",
+            ),
+            (
+                "AS_CONFED_SEQUENCE 65010 AS_SEQUENCE 65001",
+                "( 65010 ) 65001",
+            ),
+            ("AS_CONFED_SET: 65020", "({ 65020 })"),
+        ];
+        for (path, expected) in tests {
+            let normalized = normalize_as_path(path);
+            let normalized: Vec<&str> =
+                normalized.split_whitespace().collect();
+            let expected: Vec<&str> = expected.split_whitespace().collect();
+            assert_eq!(normalized, expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_parse_as_path_bird2_bird3() {
+        let parse_file = |path: &str| -> Vec<Route> {
+            let file = File::open(path).unwrap();
+            let reader = BufReader::new(file);
+            BlockIterator::new(reader, &RE_ROUTES_START)
+                .flat_map(|block| PrefixGroup::parse(block).unwrap())
+                .collect()
+        };
+        let bird2 = parse_file("tests/birdc/show-route-all-as-set-bird2");
+        let bird3 = parse_file("tests/birdc/show-route-all-as-set-bird3");
+        assert_eq!(bird2.len(), 1);
+        assert_eq!(bird3.len(), 1);
+
+        let (bird2, bird3) = (&bird2[0].bgp, &bird3[0].bgp);
+        assert_eq!(bird2.as_path_segments, bird3.as_path_segments);
+        assert_eq!(bird2.as_path, bird3.as_path);
+        assert_eq!(bird2.as_path, vec![65000, 65010, 65001, 65002, 65003]);
+        assert_eq!(bird2.as_path_segments.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_route_meta_special_via() {
+        let mut route = Route::default();
+        let next = parse_route_meta(&mut route, " \tvia dev eth0").unwrap();
+        assert_eq!(next, State::Meta);
+        assert_eq!(route.interface, "eth0");
+        assert_eq!(route.gateway, "");
+
+        let mut route = Route::default();
+        parse_route_meta(&mut route, "\tdev vx0").unwrap();
+        assert_eq!(route.interface, "vx0");
+        assert_eq!(
+            route.next_hops,
+            vec![NextHop {
+                gateway: "".into(),
+                interface: "vx0".into(),
+                weight: None,
+            }]
+        );
+
+        let mut route = Route::default();
+        let next = parse_route_meta(&mut route, " \tvia recursive").unwrap();
+        assert_eq!(next, State::Meta);
+        assert_eq!(route.dest, Some("recursive".into()));
+        assert_eq!(route.gateway, "");
+    }
+
+    #[test]
+    fn test_parse_community() {
+        let community = parse_community("(65000,1)").unwrap();
+        assert_eq!((community.0, community.1), (65000, 1));
+        let community = parse_community("65000:2").unwrap();
+        assert_eq!((community.0, community.1), (65000, 2));
+        assert!(parse_community("").is_err());
+        assert!(parse_community("(65000)").is_err());
+    }
+
+    #[test]
+    fn test_parse_route_communities_without_braces() {
+        let mut route = Route::default();
+        let state = State::Communities(CommunityType::Standard);
+        let line = " \tBGP.community: 65000:1 65000:2";
+        let state = parse_line(&mut route, state, line).unwrap();
+        let line = " \t\t65000:3";
+        parse_line(&mut route, state, line).unwrap();
+
+        let communities: Vec<(u32, u32)> =
+            route.bgp.communities.iter().map(|c| (c.0, c.1)).collect();
+        assert_eq!(communities, vec![(65000, 1), (65000, 2), (65000, 3)]);
+    }
+
+    #[test]
+    fn test_parse_route_multipath() {
+        let file = File::open("tests/birdc/show-route-all-ecmp").unwrap();
+        let routes: Vec<Route> =
+            BlockIterator::new(BufReader::new(file), &RE_ROUTES_START)
+                .flat_map(|block| PrefixGroup::parse(block).unwrap())
+                .collect();
+        assert_eq!(routes.len(), 1);
+
+        let route = &routes[0];
+        assert_eq!(route.gateway, "111.111.111.1");
+        assert_eq!(route.interface, "vx0");
+        assert_eq!(
+            route.next_hops,
+            vec![
+                NextHop {
+                    gateway: "111.111.111.1".into(),
+                    interface: "vx0".into(),
+                    weight: Some(1),
+                },
+                NextHop {
+                    gateway: "111.111.111.2".into(),
+                    interface: "vx1".into(),
+                    weight: Some(2),
+                },
+            ]
+        );
+        assert_eq!(route.bgp.as_path, vec![65003]);
+    }
+
+    #[test]
+    fn test_parse_route_wrapped_communities() {
+        let file =
+            File::open("tests/birdc/show-route-all-wrapped-communities")
+                .unwrap();
+        let routes: Vec<Route> =
+            BlockIterator::new(BufReader::new(file), &RE_ROUTES_START)
+                .flat_map(|block| PrefixGroup::parse(block).unwrap())
+                .collect();
+        assert_eq!(routes.len(), 1);
+
+        let bgp = &routes[0].bgp;
+        let communities: Vec<Community> =
+            (1..=8).map(|i| Community(65000, i)).collect();
+        assert_eq!(bgp.communities, communities);
+        assert_eq!(bgp.ext_communities.len(), 2);
+        assert_eq!(bgp.ext_communities[1].2, 2);
+        let large_communities: Vec<LargeCommunity> =
+            (1..=4).map(|i| LargeCommunity(65000, 0, i)).collect();
+        assert_eq!(bgp.large_communities, large_communities);
+    }
+
+    #[test]
+    fn test_parse_route_unreachable() {
+        let block: Block = vec![
+            "1007-10.0.0.0/8  unreachable [static1 2023-04-19 09:08:03] * (200)"
+                .into(),
+            " \tunreachable".into(),
+            "1008-\tType: static univ".into(),
+        ];
+        let routes = PrefixGroup::parse(block).unwrap();
+        assert_eq!(routes.len(), 1);
+        let route = &routes[0];
+        assert_eq!(route.network, "10.0.0.0/8");
+        assert_eq!(route.neighbor_id, Some("static1".into()));
+        assert_eq!(route.metric, 200);
+        assert_eq!(route.dest, Some("unreachable".into()));
+        assert_eq!(route.gateway, "");
+        assert_eq!(route.route_type, vec!["static", "univ"]);
+    }
+
+    #[test]
+    fn test_parse_route_type_ospf() {
+        let tests = vec![
+            ("OSPF-E2 univ", "OSPF", Some("E2")),
+            ("OSPF-IA univ", "OSPF", Some("IA")),
+            ("BGP univ", "BGP", None),
+        ];
+        for (route_type, kind, ospf_type) in tests {
+            let mut route = Route::default();
+            let line = format!("1008-\tType: {}", route_type);
+            parse_route_meta(&mut route, &line).unwrap();
+            assert_eq!(route.route_type.join(" "), route_type);
+            assert_eq!(route.protocol_kind.as_deref(), Some(kind));
+            assert_eq!(route.ospf_type.as_deref(), ospf_type);
+        }
+    }
+
+    #[test]
+    fn test_parse_route_blackhole() {
+        let file = File::open("tests/birdc/show-route-all-blackhole").unwrap();
+        let routes: Vec<Route> =
+            BlockIterator::new(BufReader::new(file), &RE_ROUTES_START)
+                .flat_map(|block| PrefixGroup::parse(block).unwrap())
+                .collect();
+        assert_eq!(routes.len(), 2);
+
+        let route = &routes[0];
+        assert_eq!(route.network, "10.0.0.0/8");
+        assert_eq!(route.neighbor_id, Some("static1".into()));
+        assert_eq!(route.dest, Some("blackhole".into()));
+
+        let route = &routes[1];
+        assert_eq!(route.network, "10.1.0.0/16");
+        assert_eq!(route.neighbor_id, None);
+        assert_eq!(route.dest, Some("unreachable".into()));
+        assert_eq!(route.metric, 200);
+        assert_eq!(route.route_type, vec!["static", "univ"]);
+
+        let json = serde_json::to_value(route).unwrap();
+        assert_eq!(json["gateway"], "");
+    }
+
     #[test]
     fn test_parse_large_communities() {
         let line = "(57463, 0, 1120) (57463, 0, 5408) (57463, 0, 6461)";
         let communities = parse_large_communities(line).unwrap();
-        println!("{:?}", communities);
+        assert_eq!(communities.len(), 3);
+        assert_eq!(communities[1].2, 5408);
+    }
+
+    #[test]
+    fn test_parse_ext_communities() {
+        let line = "(rt, 271042, 0) (generic, 0x43000000, 0x1)";
+        let communities = parse_ext_communities(line).unwrap();
+        assert_eq!(communities.len(), 2);
+        assert_eq!(communities[0].0, "rt");
+        assert_eq!(communities[0].1, 271042);
+        assert_eq!(communities[0].to_u64(), None);
+
+        assert_eq!(communities[1].0, "generic");
+        assert_eq!(communities[1].1, 0x43000000);
+        assert_eq!(communities[1].to_u64(), Some(0x43000000_00000001));
     }
 
     #[test]
@@ -390,6 +1336,163 @@ mod tests {
         println!("{:?}", route);
     }
 
+    #[test]
+    fn test_parse_route_evpn() {
+        let block = r#"1007-evpn mac 10.0.0.1:100 0 aa:bb:cc:dd:ee:ff *   unicast [R1 2023-04-19 09:35:57] * (100) [AS65001i]
+ 	via 172.31.193.103 on vx0
+1008-	Type: BGP univ
+1012-	BGP.origin: IGP
+ 	BGP.as_path: 65001
+ 	BGP.next_hop: 172.31.193.103
+ 	BGP.local_pref: 100
+ 	BGP.ext_community: (rt, 65001, 100)
+ 	BGP.encap: vxlan
+ 	BGP.vni: 10100
+ 	BGP.large_community: (6695, 1000, 1)
+"#;
+        let block: Vec<String> =
+            block.lines().map(|s| s.to_string()).collect();
+        let route = Route::parse(block.clone()).unwrap();
+
+        assert_eq!(route.bgp.encap, Some("vxlan".into()));
+        assert_eq!(route.bgp.vni, Some(10100));
+        assert_eq!(route.bgp.ext_communities.len(), 1);
+        assert_eq!(route.bgp.large_communities.len(), 1);
+
+        // A malformed VNI does not drop the route
+        let mut block = block;
+        block[9] = " \tBGP.vni: 10100x".into();
+        let route = Route::parse(block).unwrap();
+        assert_eq!(route.bgp.vni, None);
+        assert_eq!(route.bgp.encap, Some("vxlan".into()));
+    }
+
+    #[test]
+    fn test_parse_route_bgp_lu() {
+        let block = r#"1007-10.0.0.1/32  unicast [R1 2023-04-19 09:35:57] * (100) [AS65001i]
+ 	via 172.31.193.103 on vx0
+1008-	Type: BGP univ
+1012-	BGP.origin: IGP
+ 	BGP.as_path: 65001
+ 	BGP.next_hop: 172.31.193.103
+ 	BGP.mpls_label_stack: 24001
+ 	BGP.local_pref: 100
+"#;
+        let block: Vec<String> =
+            block.lines().map(|s| s.to_string()).collect();
+        let route = Route::parse(block).unwrap();
+        assert_eq!(route.bgp.bgp_label, Some(24001));
+        assert_eq!(route.bgp.local_pref, Some(100));
+
+        assert_eq!(parse_bgp_label("100/200"), Some(100));
+        assert_eq!(parse_bgp_label(" 3 "), Some(3));
+        assert_eq!(parse_bgp_label(""), None);
+    }
+
+    #[test]
+    fn test_parse_route_unset_med() {
+        let block: Block = vec![
+            "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:35:57] * (100) [AS1i]"
+                .into(),
+            " \tvia 172.31.193.103 on vx0".into(),
+            "1008-\tType: BGP univ".into(),
+            "1012-\tBGP.origin: IGP".into(),
+            " \tBGP.as_path: 1".into(),
+            " \tBGP.next_hop: 172.31.193.103".into(),
+            " \tBGP.med: -".into(),
+            " \tBGP.local_pref: -".into(),
+            " \tBGP.community: (65000,1)".into(),
+        ];
+        let route = Route::parse(block).unwrap();
+        assert_eq!(route.bgp.med, None);
+        assert_eq!(route.bgp.local_pref, None);
+        assert_eq!(route.bgp.communities.len(), 1);
+
+        assert_eq!(parse_optional_value("100").unwrap(), Some(100));
+        assert_eq!(parse_optional_value(" 0 ").unwrap(), Some(0));
+        assert!(parse_optional_value("x").is_err());
+    }
+
+    #[test]
+    fn test_parse_route_next_hop_resolved() {
+        let block: Block = vec![
+            "1007-10.0.0.0/8  unicast [R1 2023-04-19 09:35:57] * (100) [AS1i]"
+                .into(),
+            " \tvia 172.31.193.103 on vx0".into(),
+            "1008-\tType: BGP univ".into(),
+            "1012-\tBGP.origin: IGP".into(),
+            " \tBGP.as_path: 1".into(),
+            " \tBGP.next_hop: 172.31.193.103".into(),
+        ];
+        let route = Route::parse(block.clone()).unwrap();
+        assert!(!route.next_hop_resolved);
+
+        let mut block = block;
+        block[5] = " \tBGP.next_hop: 10.23.42.1".into();
+        let route = Route::parse(block).unwrap();
+        assert_eq!(route.gateway, "172.31.193.103");
+        assert_eq!(route.bgp.next_hop, "10.23.42.1");
+        assert!(route.next_hop_resolved);
+    }
+
+    #[test]
+    fn test_redact_private_asns() {
+        let mut route = Route::default();
+        route.bgp.as_path = vec![553, 64512, 65534, 65535, 4200000001, 60824];
+        route.bgp.as_path_segments = vec![
+            AsPathSegment::Sequence(vec![553, 64512, 65534, 65535]),
+            AsPathSegment::ConfedSequence(vec![4200000001]),
+            AsPathSegment::Sequence(vec![60824]),
+        ];
+        redact_private_asns(&mut route);
+        assert_eq!(route.bgp.as_path, vec![553, 65535, 60824]);
+        assert_eq!(
+            route.bgp.as_path_segments,
+            vec![
+                AsPathSegment::Sequence(vec![553, 65535]),
+                AsPathSegment::Sequence(vec![60824]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redact_private_asns_peer_origin() {
+        let mut route = Route::default();
+        route.bgp.origin_as = Some(65001);
+        route.bgp.as_path = vec![64512, 553, 64513, 65001];
+        route.bgp.as_path_segments = vec![
+            AsPathSegment::Sequence(vec![64512, 553]),
+            AsPathSegment::Sequence(vec![64513]),
+            AsPathSegment::Set(vec![65001]),
+        ];
+        redact_private_asns(&mut route);
+        assert_eq!(route.bgp.as_path, vec![64512, 553, 65001]);
+        assert_eq!(
+            route.bgp.as_path_segments,
+            vec![
+                AsPathSegment::Sequence(vec![64512, 553]),
+                AsPathSegment::Set(vec![65001]),
+            ]
+        );
+        assert_eq!(route.bgp.origin_as, Some(65001));
+    }
+
+    #[test]
+    fn test_truncate_communities() {
+        let mut route = Route::default();
+        route.bgp.communities = (0..5).map(|i| Community(65000, i)).collect();
+        route.bgp.large_communities = vec![LargeCommunity(6695, 1000, 1)];
+
+        truncate_communities(&mut route, 5);
+        assert!(!route.communities_truncated);
+        assert_eq!(route.bgp.communities.len(), 5);
+
+        truncate_communities(&mut route, 2);
+        assert!(route.communities_truncated);
+        assert_eq!(route.bgp.communities.len(), 2);
+        assert_eq!(route.bgp.large_communities.len(), 1);
+    }
+
     /*
     #[test]
     fn test_routes_reader() {
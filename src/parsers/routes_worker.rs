@@ -1,10 +1,12 @@
 use std::{
     num::NonZeroUsize,
+    panic,
     sync::{Arc, Mutex},
     thread,
 };
 
 use anyhow::Result;
+use thiserror::Error;
 use tokio::sync::mpsc::{
     error::TryRecvError, unbounded_channel, UnboundedReceiver, UnboundedSender,
 };
@@ -14,8 +16,17 @@ use crate::parsers::{
     routes::PrefixGroup,
 };
 
-type BlockQueue = Arc<Mutex<UnboundedReceiver<Block>>>;
+/// A block of routes and the table they belong to
+pub type TableBlock = (Option<String>, Block);
+
+type BlockQueue = Arc<Mutex<UnboundedReceiver<TableBlock>>>;
 type ResultsQueue = UnboundedSender<Result<PrefixGroup>>;
+type ParseFn = fn(Block) -> Result<PrefixGroup>;
+
+/// A worker failed to parse a block
+#[derive(Error, Debug)]
+#[error("routes worker failed")]
+pub struct WorkerError;
 
 pub struct RoutesWorker {
     id: usize,
@@ -29,17 +40,37 @@ impl RoutesWorker {
 
     /// Spawn a new routes worker and create a response and
     /// request channel.
-    pub fn spawn(&self, block_queue: BlockQueue, results_queue: ResultsQueue) {
+    pub fn spawn(
+        &self,
+        parse: ParseFn,
+        block_queue: BlockQueue,
+        results_queue: ResultsQueue,
+    ) {
         tracing::debug!("routes worker {} started.", self.id);
+        let id = self.id;
         thread::spawn(move || loop {
             let block = {
                 let mut queue = block_queue.lock().unwrap();
                 queue.try_recv()
             };
             match block {
-                Ok(block) => {
-                    let routes = PrefixGroup::parse(block);
-                    results_queue.send(routes).unwrap();
+                Ok((table, block)) => {
+                    // A panic while parsing fails the request,
+                    // but the worker keeps running.
+                    let routes = panic::catch_unwind(move || parse(block))
+                        .unwrap_or_else(|_| {
+                            tracing::error!("routes worker {} panicked", id);
+                            Err(WorkerError.into())
+                        })
+                        .map(|mut routes| {
+                            for route in routes.iter_mut() {
+                                route.table.clone_from(&table);
+                            }
+                            routes
+                        });
+                    if results_queue.send(routes).is_err() {
+                        break; // Nobody is waiting for results
+                    }
                 }
                 Err(TryRecvError::Empty) => {
                     continue;
@@ -59,10 +90,20 @@ pub struct RoutesWorkerPool {}
 impl RoutesWorkerPool {
     /// Create new worker pool and spawn workers
     pub fn spawn() -> (
-        UnboundedSender<Block>,
+        UnboundedSender<TableBlock>,
+        UnboundedReceiver<Result<PrefixGroup>>,
+    ) {
+        Self::spawn_with(PrefixGroup::parse)
+    }
+
+    /// Create new worker pool using a parse function
+    fn spawn_with(
+        parse: ParseFn,
+    ) -> (
+        UnboundedSender<TableBlock>,
         UnboundedReceiver<Result<PrefixGroup>>,
     ) {
-        let (blocks_tx, blocks_rx) = unbounded_channel::<Block>();
+        let (blocks_tx, blocks_rx) = unbounded_channel::<TableBlock>();
         let (results_tx, results_rx) =
             unbounded_channel::<Result<PrefixGroup>>();
 
@@ -80,7 +121,7 @@ impl RoutesWorkerPool {
         // Start workers
         for id in 0..num_workers {
             let worker = RoutesWorker::new(id);
-            worker.spawn(blocks_queue.clone(), results_tx.clone());
+            worker.spawn(parse, blocks_queue.clone(), results_tx.clone());
         }
 
         (blocks_tx, results_rx)
@@ -114,7 +155,7 @@ mod tests {
 
         thread::spawn(move || {
             for block in blocks {
-                blocks_tx.send(block).unwrap();
+                blocks_tx.send((None, block)).unwrap();
             }
         });
 
@@ -125,4 +166,26 @@ mod tests {
 
         println!("collected routes: {}", routes.len());
     }
+
+    #[tokio::test]
+    async fn test_routes_worker_panic() {
+        fn parse(block: Block) -> Result<PrefixGroup> {
+            if block[0] == "panic" {
+                panic!("failed to parse block");
+            }
+            Ok(vec![Route::default()])
+        }
+
+        let (blocks_tx, mut results_rx) = RoutesWorkerPool::spawn_with(parse);
+        blocks_tx.send((None, vec!["panic".into()])).unwrap();
+        let err = results_rx.recv().await.unwrap().unwrap_err();
+        assert!(err.is::<WorkerError>());
+
+        // The workers are still running
+        let table = Some("master4".to_string());
+        blocks_tx.send((table.clone(), vec!["ok".into()])).unwrap();
+        let routes = results_rx.recv().await.unwrap().unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].table, table);
+    }
 }
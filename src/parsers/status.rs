@@ -1,3 +1,5 @@
+use std::net::IpAddr;
+
 use anyhow::Result;
 
 use crate::parsers::parser::{Block, Parse};
@@ -7,23 +9,50 @@ impl Parse for BirdStatus {
     /// Parse the status output of bird response
     fn parse(lines: Block) -> Result<BirdStatus> {
         let mut status = BirdStatus::default();
+        let mut in_message = false;
         for line in lines {
+            // The message may continue on the following lines,
+            // the last line repeats the control code.
+            if in_message && line.starts_with(' ') {
+                parse_message_continuation(&mut status, &line);
+                continue;
+            }
+            if in_message && is_message(&line) {
+                let line = line.get(5..).unwrap_or("");
+                parse_message_continuation(&mut status, line);
+                continue;
+            }
+            in_message = is_message(&line);
             parse_line(&mut status, &line)?;
         }
         Ok(status)
     }
 }
 
+/// Parse the version from the greeting, e.g. `0001 BIRD v2.0.10 ready.`
+/// The version is the first token starting with a digit,
+/// a leading `v` is stripped.
 fn parse_version(status: &mut BirdStatus, line: &str) {
-    let tokens: Vec<&str> = line.split_whitespace().collect();
-    let version = tokens[2].to_string();
-    status.version = version;
+    let version = line
+        .split_whitespace()
+        .skip(1)
+        .map(|token| token.strip_prefix('v').unwrap_or(token))
+        .find(|token| token.starts_with(|c: char| c.is_ascii_digit()));
+    if let Some(version) = version {
+        status.version = version.to_string();
+    }
 }
 
+/// Parse the router id. The id is the first token which is
+/// an IP address, ignoring trailing punctuation.
 fn parse_router_id(status: &mut BirdStatus, line: &str) {
-    let tokens: Vec<&str> = line.split_whitespace().collect();
-    let router_id = tokens[tokens.len() - 1].to_string();
-    status.router_id = router_id;
+    let router_id = line
+        .split_whitespace()
+        .map(|token| token.trim_end_matches(['.', ',']))
+        .find(|token| token.parse::<IpAddr>().is_ok());
+    if let Some(router_id) = router_id {
+        status.router_id = router_id.to_string();
+    }
 }
 
 fn parse_server_time(status: &mut BirdStatus, line: &str) {
@@ -41,11 +70,21 @@ fn parse_last_reconfig(status: &mut BirdStatus, line: &str) {
     status.last_reconfig = s.to_string(); // unparsed.
 }
 
+/// Check if the line starts the status message
+fn is_message(line: &str) -> bool {
+    line.starts_with("0013 ") || line.starts_with("0013-")
+}
+
 fn parse_message(status: &mut BirdStatus, line: &str) {
-    let message = line.strip_prefix("0013 ").unwrap_or("");
+    let message = line.get(5..).unwrap_or("");
     status.message = message.to_string();
 }
 
+fn parse_message_continuation(status: &mut BirdStatus, line: &str) {
+    status.message.push('\n');
+    status.message.push_str(line.trim());
+}
+
 fn parse_line(status: &mut BirdStatus, line: &str) -> Result<()> {
     if line.starts_with("0001 ") {
         parse_version(status, line);
@@ -57,7 +96,7 @@ fn parse_line(status: &mut BirdStatus, line: &str) -> Result<()> {
         parse_last_reboot(status, line);
     } else if line.starts_with(" Last reconfiguration") {
         parse_last_reconfig(status, line);
-    } else if line.starts_with("0013 ") {
+    } else if is_message(line) {
         parse_message(status, line);
     }
     Ok(())
@@ -84,5 +123,53 @@ mod tests {
         assert_eq!(status.current_server, "2023-05-10 14:27:32");
         assert_eq!(status.last_reboot, "2023-05-10 11:34:49");
         assert_eq!(status.last_reconfig, "2023-05-10 11:34:49");
+        assert_eq!(status.message, "Daemon is up and running");
+    }
+
+    #[test]
+    fn test_parse_router_id() {
+        let tests = vec![
+            ("1011-Router ID is 1.2.3.4", "1.2.3.4"),
+            ("1011-Router ID is 1.2.3.4.", "1.2.3.4"),
+            ("1011-Router ID is 1.2.3.4 (from config)", "1.2.3.4"),
+            ("1011-Router ID is unknown", ""),
+        ];
+        for (line, router_id) in tests {
+            let mut status = BirdStatus::default();
+            parse_router_id(&mut status, line);
+            assert_eq!(status.router_id, router_id, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_parse_version() {
+        let tests = vec![
+            ("0001 BIRD 2.16.1 ready.", "2.16.1"),
+            ("0001 BIRD v2.0.10 ready on rs1.example.", "2.0.10"),
+            (
+                "0001 BIRD 2.14+branch.master.263a9d4f ready.",
+                "2.14+branch.master.263a9d4f",
+            ),
+            ("0001 BIRD ready.", ""),
+        ];
+        for (line, expected) in tests {
+            let mut status = BirdStatus::default();
+            parse_version(&mut status, line);
+            assert_eq!(status.version, expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_parse_multiline_message() {
+        let file = File::open("tests/birdc/show-status-shutdown").unwrap();
+        let reader = BufReader::new(file);
+        let block =
+            reader.lines().map(|l| l.unwrap()).collect::<Vec<String>>();
+        let status = BirdStatus::parse(block).unwrap();
+        assert_eq!(status.router_id, "111.111.111.111");
+        assert_eq!(
+            status.message,
+            "Shutdown in progress\nGraceful restart requested\nby administrator"
+        );
     }
 }
@@ -17,11 +17,16 @@ pub struct CacheStatus {
     pub cached_at: CacheInfo,
 }
 
+/// Version of the response schema. This must be increased
+/// with every breaking change of the responses.
+pub const ENVELOPE_VERSION: u32 = 2;
+
 /// ApiStatus
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ApiStatus {
     #[serde(rename = "Version")]
     pub version: String,
+    pub envelope_version: u32,
     pub result_from_cache: bool,
     pub cache_status: Option<CacheStatus>,
 }
@@ -30,6 +35,7 @@ impl Default for ApiStatus {
     fn default() -> Self {
         ApiStatus {
             version: "0.0.1".to_string(),
+            envelope_version: ENVELOPE_VERSION,
             result_from_cache: false,
             cache_status: None,
         }
@@ -69,55 +75,324 @@ pub struct Neighbor {
     pub id: String,
     pub address: String,
     pub asn: u32,
+    /// ASN extracted from the protocol id, if the
+    /// neighbor AS is not known.
+    pub derived_asn: Option<u32>,
     pub state: String,
+    pub bgp_state: String,
     pub description: String,
     pub routes: RoutesCount,
     pub uptime: f64, // seconds
     pub since: DateTime<Utc>,
     pub last_error: String,
+    pub last_error_class: Option<String>,
     #[serde(rename = "routeserver_id")]
     pub route_server_id: String,
 
+    /// Routes of all channels
     pub routes_received: u32,
     pub routes_filtered: u32,
     pub routes_accepted: u32,
     pub routes_exported: u32,
+
+    /// Channels of the session, e.g. `ipv4` and `ipv6`
+    pub channels: Vec<Channel>,
+
+    /// Capabilities advertised by the neighbor
+    pub capabilities: Vec<String>,
+
+    /// The session is external (eBGP) or internal (iBGP)
+    pub session_type: Option<String>,
 }
 
 pub type NeighborsMap = HashMap<String, Neighbor>;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// A channel of a BGP session
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Channel {
+    /// Address family, e.g. `ipv4`
+    pub name: String,
+    pub state: String,
+    pub table: String,
+
+    pub receive_limit: Option<u32>,
+    pub import_limit: Option<u32>,
+    pub export_limit: Option<u32>,
+
+    /// Graceful restart state of the channel
+    pub gr_state: Option<String>,
+
+    /// Filters of the channel and their kind:
+    /// `accept`, `reject`, `named` or `inline`
+    pub input_filter: Option<String>,
+    pub input_filter_kind: Option<String>,
+    pub output_filter: Option<String>,
+    pub output_filter_kind: Option<String>,
+}
+
+/// Routes summary of a neighbor
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RoutesSummary {
+    pub received: u32,
+    pub filtered: u32,
+    pub accepted: u32,
+}
+
+impl From<&Neighbor> for RoutesSummary {
+    fn from(neighbor: &Neighbor) -> Self {
+        RoutesSummary {
+            received: neighbor.routes_received,
+            filtered: neighbor.routes_filtered,
+            accepted: neighbor.routes_accepted,
+        }
+    }
+}
+
+/// Number of routes in a table
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RouteCount {
+    pub table: String,
+    pub routes: u32,
+    pub routes_total: u32,
+    pub networks: u32,
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
 pub struct Community(pub u32, pub u32);
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
 pub struct LargeCommunity(pub u32, pub u32, pub u32);
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ExtCommunity(pub String, pub u32, pub u32);
 
+impl ExtCommunity {
+    /// Get the value of a generic ext community as a
+    /// single integer.
+    pub fn to_u64(&self) -> Option<u64> {
+        if self.0 != "generic" {
+            return None;
+        }
+        Some((self.1 as u64) << 32 | self.2 as u64)
+    }
+}
+
+/// A segment of the AS path
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "asns", rename_all = "snake_case")]
+pub enum AsPathSegment {
+    Sequence(Vec<u32>),
+    Set(Vec<u32>),
+    ConfedSequence(Vec<u32>),
+    ConfedSet(Vec<u32>),
+}
+
+impl AsPathSegment {
+    /// Create an empty segment
+    pub fn new(set: bool, confed: bool) -> Self {
+        match (set, confed) {
+            (false, false) => AsPathSegment::Sequence(vec![]),
+            (true, false) => AsPathSegment::Set(vec![]),
+            (false, true) => AsPathSegment::ConfedSequence(vec![]),
+            (true, true) => AsPathSegment::ConfedSet(vec![]),
+        }
+    }
+
+    /// Get the ASNs in the segment
+    pub fn asns(&self) -> &Vec<u32> {
+        match self {
+            AsPathSegment::Sequence(asns)
+            | AsPathSegment::Set(asns)
+            | AsPathSegment::ConfedSequence(asns)
+            | AsPathSegment::ConfedSet(asns) => asns,
+        }
+    }
+
+    /// Get the ASNs in the segment for modification
+    pub fn asns_mut(&mut self) -> &mut Vec<u32> {
+        match self {
+            AsPathSegment::Sequence(asns)
+            | AsPathSegment::Set(asns)
+            | AsPathSegment::ConfedSequence(asns)
+            | AsPathSegment::ConfedSet(asns) => asns,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct BGPInfo {
     pub origin: String,
+    /// Origin AS from the route header, e.g. `[AS7545i]`
+    pub origin_as: Option<u32>,
+    /// All ASNs of the path, including sets and
+    /// confederation segments
     pub as_path: Vec<u32>,
+    pub as_path_segments: Vec<AsPathSegment>,
     pub next_hop: String,
     pub communities: Vec<Community>,
     pub large_communities: Vec<LargeCommunity>,
     pub ext_communities: Vec<ExtCommunity>,
-    pub local_pref: u32,
-    pub med: u32,
+    /// Local preference and MED are None if unset
+    pub local_pref: Option<u32>,
+    pub med: Option<u32>,
+
+    /// Tunnel encapsulation, e.g. for EVPN/VXLAN routes
+    pub encap: Option<String>,
+    pub vni: Option<u32>,
+
+    /// Label of a BGP labeled unicast (BGP-LU) route
+    pub bgp_label: Option<u32>,
+}
+
+impl BGPInfo {
+    /// Get the length of the AS path as used in the best path
+    /// selection: An AS set counts as 1, confederation
+    /// segments are not counted.
+    pub fn as_path_len(&self) -> usize {
+        self.as_path_segments
+            .iter()
+            .map(|segment| match segment {
+                AsPathSegment::Sequence(asns) => asns.len(),
+                AsPathSegment::Set(asns) => asns.len().min(1),
+                AsPathSegment::ConfedSequence(_)
+                | AsPathSegment::ConfedSet(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Get the AS path with prepending removed: consecutive
+    /// repetitions of an ASN are collapsed into one.
+    pub fn as_path_dedup(&self) -> Vec<String> {
+        let mut path = self.as_path.clone();
+        path.dedup();
+        path.iter().map(|asn| asn.to_string()).collect()
+    }
+}
+
+/// A next hop of a (multipath) route
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NextHop {
+    pub gateway: String,
+    pub interface: String,
+    pub weight: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Route {
     pub neighbor_id: Option<String>,
+    /// Description of the neighbor, only set if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub neighbor_description: Option<String>,
     pub network: String,
+    /// Same as the first next hop
     pub interface: String,
     pub gateway: String,
+    /// All next hops, multipath routes have more than one
+    pub next_hops: Vec<NextHop>,
+    /// Special destination like unreachable or blackhole
+    pub dest: Option<String>,
+    /// Same as the preference, kept for Alice-LG compatibility
     pub metric: u32,
+    /// Preference of the route, e.g. `(100)` in the header
+    pub preference: u32,
     pub bgp: BGPInfo,
     pub age: f64,
+    /// Time of the last change of the route
+    pub age_timestamp: Option<DateTime<Utc>>,
     #[serde(rename = "type")]
     pub route_type: Vec<String>,
+    /// Protocol of the route type, e.g. `OSPF` for `OSPF-E2`
+    pub protocol_kind: Option<String>,
+    /// OSPF route subtype, e.g. `E2` or `IA`
+    pub ospf_type: Option<String>,
+    /// The route was selected as the best route (`*`)
     pub primary: bool,
+    /// The route is valid and in the table, but not
+    /// necessarily selected
+    pub active: bool,
+    /// Number of routes for the same prefix. If the routes of
+    /// the prefix were truncated, only the kept routes are counted.
+    pub group_size: usize,
     pub learnt_from: Option<String>,
+    pub communities_truncated: bool,
+    /// The gateway differs from the BGP next hop
+    pub next_hop_resolved: bool,
+    /// The route was rejected by a filter
+    pub filtered: bool,
+    /// The table the route was listed in
+    pub table: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_as_path_len() {
+        let tests = vec![
+            (vec![AsPathSegment::Sequence(vec![553, 60824, 60824])], 3),
+            (
+                vec![
+                    AsPathSegment::Sequence(vec![65000]),
+                    AsPathSegment::Set(vec![65001, 65002]),
+                    AsPathSegment::Sequence(vec![65003]),
+                ],
+                3,
+            ),
+            (
+                vec![
+                    AsPathSegment::ConfedSequence(vec![65010, 65011]),
+                    AsPathSegment::ConfedSet(vec![65020]),
+                    AsPathSegment::Sequence(vec![65000, 65003]),
+                ],
+                2,
+            ),
+            (vec![], 0),
+        ];
+        for (segments, len) in tests {
+            let bgp = BGPInfo {
+                as_path_segments: segments,
+                ..Default::default()
+            };
+            assert_eq!(bgp.as_path_len(), len);
+        }
+    }
+
+    #[test]
+    fn test_as_path_dedup() {
+        let tests = vec![
+            (vec![64500, 64500, 64500, 1299], vec!["64500", "1299"]),
+            (vec![64500, 1299, 64500], vec!["64500", "1299", "64500"]),
+            (vec![], vec![]),
+        ];
+        for (as_path, dedup) in tests {
+            let bgp = BGPInfo {
+                as_path,
+                ..Default::default()
+            };
+            assert_eq!(bgp.as_path_dedup(), dedup);
+        }
+    }
 }